@@ -1,9 +1,34 @@
+use std::{ f64::consts::PI, time::Duration };
+
 use anyhow::Result;
 use edflib::*;
 
+const SAMPLE_RATE: usize = 200;
+const DURATION: usize = 10;
+
 pub fn main() -> Result<()> {
     let path = "generator.edf";
-    let edf = Edf::new(path.into());
-    edf.open_file_writeonly().unwrap();
+    let edf = EdfWriterBuilder::new(path.into())
+        .signal(
+            SignalSpec::new("sine 1 Hz".into(), "uV".into(), SAMPLE_RATE as f64, (
+                -100.0,
+                100.0,
+            ), (-32768, 32767)).with_prefilter("HP:0.1Hz LP:75Hz")
+        )
+        .equipment("edflib generator")
+        .build()?;
+
+    for second in 0..DURATION {
+        let samples: Vec<f64> = (0..SAMPLE_RATE)
+            .map(|i| {
+                let t = (second as f64) + (i as f64) / (SAMPLE_RATE as f64);
+                100.0 * (2.0 * PI * t).sin()
+            })
+            .collect();
+        edf.write_samples(&samples)?;
+    }
+
+    edf.write_annotation(Duration::ZERO, None, "Recording starts")?;
+    edf.finish()?;
     Ok(())
 }
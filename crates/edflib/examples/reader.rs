@@ -0,0 +1,47 @@
+use anyhow::Result;
+use edflib::*;
+
+pub fn main() -> Result<()> {
+    let path = "generator.edf";
+    let edf = EdfReader::open(path)?;
+    let header = edf.header();
+
+    println!("filetype: {:?}", header.filetype);
+    println!("signals: {}", edf.number_of_signals());
+    println!("file duration: {:?}", header.file_duration);
+    println!(
+        "start: {}-{:02}-{:02} {:02}:{:02}:{:02}",
+        header.start.year,
+        header.start.month,
+        header.start.day,
+        header.start.hour,
+        header.start.minute,
+        header.start.second
+    );
+    println!("patient: {}", header.patient_name);
+    println!("equipment: {}", header.equipment);
+    println!("datarecords: {}", header.datarecords_in_file);
+    println!("annotations: {}", header.annotations_in_file);
+
+    for signal in edf.signals() {
+        println!(
+            "{}: {} samples/record, {} .. {} {}",
+            signal.label,
+            signal.samples_in_datarecord,
+            signal.physical_minimum,
+            signal.physical_maximum,
+            signal.physical_dimension
+        );
+    }
+
+    for annotation in edf.annotations() {
+        let annotation = annotation?;
+        println!(
+            "annotation: onset {:?}, duration {:?}, \"{}\"",
+            annotation.onset,
+            annotation.duration,
+            annotation.text
+        );
+    }
+    Ok(())
+}
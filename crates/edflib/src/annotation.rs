@@ -0,0 +1,125 @@
+#[cfg(feature = "ffi")]
+use std::{ mem::MaybeUninit, os::raw::c_int };
+use std::time::Duration;
+
+#[cfg(feature = "ffi")]
+use edflib_sys::*;
+
+use crate::{ error::*, reader::EdfReader, utils::ticks_to_duration };
+#[cfg(feature = "ffi")]
+use crate::utils::*;
+
+/// Which annotations are read when a file is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReadAnnotations {
+    /// Skip annotations, opening very large EDF+/BDF+ files is faster.
+    DoNotRead,
+    /// Stop at the first annotation with the description "Recording ends".
+    UntilRecordingEnds,
+    #[default]
+    All,
+}
+
+#[cfg(feature = "ffi")]
+impl ReadAnnotations {
+    pub(crate) fn to_raw(self) -> c_int {
+        (match self {
+            ReadAnnotations::DoNotRead => EDFLIB_DO_NOT_READ_ANNOTATIONS,
+            ReadAnnotations::UntilRecordingEnds => EDFLIB_READ_ANNOTATIONS,
+            ReadAnnotations::All => EDFLIB_READ_ALL_ANNOTATIONS,
+        }) as c_int
+    }
+}
+
+/// An annotation (event, trigger) read from an EDF+/BDF+ file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    /// Time since the start of the recording, annotations before the start are reported as
+    /// [`EdfError::NegativeOnset`].
+    pub onset: Duration,
+    pub duration: Option<Duration>,
+    pub text: String,
+}
+
+/// An annotation as stored in the file, times in units of 100 ns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RawAnnotation {
+    pub onset: i64,
+    /// Negative when the annotation has no duration.
+    pub duration: i64,
+    pub text: String,
+}
+
+impl RawAnnotation {
+    pub(crate) fn to_annotation(
+        &self,
+        context: impl FnOnce() -> ErrorContext
+    ) -> Result<Annotation> {
+        if self.onset < 0 {
+            return Err(EdfError::NegativeOnset(self.onset, context()));
+        }
+        Ok(Annotation {
+            onset: ticks_to_duration(self.onset),
+            duration: (self.duration >= 0).then(|| ticks_to_duration(self.duration)),
+            text: self.text.clone(),
+        })
+    }
+}
+
+#[cfg(feature = "ffi")]
+impl Annotation {
+    pub(crate) fn read_raw(
+        hdl: c_int,
+        index: usize,
+        context: impl FnOnce() -> ErrorContext
+    ) -> Result<Self> {
+        let mut annot = MaybeUninit::<edflib_annotation_t>::zeroed();
+        let result = unsafe { edf_get_annotation(hdl, index as c_int, annot.as_mut_ptr()) };
+        if result < 0 {
+            return Err(EdfError::Rejected(context()));
+        }
+
+        let annot = unsafe { annot.assume_init() };
+        let raw = RawAnnotation {
+            onset: annot.onset,
+            duration: annot.duration_l,
+            text: chars_to_string(&annot.annotation),
+        };
+        raw.to_annotation(context)
+    }
+}
+
+/// Iterator over the annotations of an [`EdfReader`], see [`EdfReader::annotations`].
+pub struct Annotations<'a, 's> {
+    reader: &'a EdfReader<'s>,
+    index: i64,
+    count: i64,
+}
+
+impl<'a, 's> Annotations<'a, 's> {
+    pub(crate) fn new(reader: &'a EdfReader<'s>) -> Self {
+        Annotations { reader, index: 0, count: reader.header().annotations_in_file }
+    }
+}
+
+impl Iterator for Annotations<'_, '_> {
+    type Item = Result<Annotation>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        Some(self.reader.annotation(index as usize))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.count - self.index).max(0) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Annotations<'_, '_> {}
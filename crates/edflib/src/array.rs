@@ -0,0 +1,113 @@
+use ndarray::{ Array1, Array2 };
+#[cfg(feature = "ffi")]
+use ndarray::{ ArrayView2, Axis };
+
+use crate::{ error::*, reader::EdfReader };
+#[cfg(feature = "ffi")]
+use crate::Edf;
+
+impl EdfReader<'_> {
+    /// Reads every signal into its own array, for files with mixed sample rates.
+    ///
+    /// Every signal is read from its first sample, whatever its position was before.
+    pub fn read_all_arrays(&self) -> Result<Vec<Array1<f64>>> {
+        (0..self.number_of_signals())
+            .map(|signal| {
+                let mut reader = self.signal(signal)?;
+                reader.rewind()?;
+                let mut samples = vec![0.0; reader.info().samples_in_file.max(0) as usize];
+                let read = reader.read_into(&mut samples)?;
+                samples.truncate(read);
+                Ok(Array1::from(samples))
+            })
+            .collect()
+    }
+
+    /// Reads all signals into a channels × samples array. Every signal needs the same number of
+    /// samples, use [`EdfReader::read_all_arrays`] for mixed sample rates.
+    ///
+    /// Every signal is read from its first sample, whatever its position was before.
+    pub fn read_all_array(&self) -> Result<Array2<f64>> {
+        let context = || self.context("read_all_array");
+        let samples = self.signals().first().map_or(0, |info| info.samples_in_file.max(0));
+        let mismatch = self.signals().iter().position(|info| info.samples_in_file != samples);
+        if let Some(signal) = mismatch {
+            return Err(
+                EdfError::InvalidArgument(
+                    "signals have different sample counts".to_string(),
+                    context().signal(signal as i32)
+                )
+            );
+        }
+
+        let rows = self.number_of_signals();
+        let samples = samples as usize;
+        let mut buffer = vec![0.0; rows * samples];
+        for (signal, row) in buffer.chunks_exact_mut(samples.max(1)).enumerate() {
+            let mut reader = self.signal(signal)?;
+            reader.rewind()?;
+            if reader.read_into(row)? != samples {
+                return Err(EdfError::Rejected(context().signal(signal as i32)));
+            }
+        }
+        let array = Array2::from_shape_vec((rows, samples), buffer).unwrap();
+        Ok(array)
+    }
+}
+
+#[cfg(feature = "ffi")]
+impl Edf {
+    /// Writes a channels × samples array of physical values as whole datarecords.
+    ///
+    /// All signals need the same samples per datarecord and the number of columns has to be a
+    /// multiple of it, use [`Edf::write_record`] for mixed sample rates.
+    pub fn write_array(&self, data: ArrayView2<f64>) -> Result<()> {
+        let context = || self.context("write_array");
+        let samples_per_record = self.samples_per_record();
+        if data.nrows() != samples_per_record.len() {
+            return Err(
+                EdfError::InvalidArgument(
+                    format!(
+                        "array has {} rows, the file {} signals",
+                        data.nrows(),
+                        samples_per_record.len()
+                    ),
+                    context()
+                )
+            );
+        }
+        let per_record = samples_per_record.first().map_or(0, |&samples| samples.max(0) as usize);
+        let mismatch = samples_per_record.iter().position(|&n| (n.max(0) as usize) != per_record);
+        if let Some(signal) = mismatch {
+            return Err(
+                EdfError::InvalidArgument(
+                    "signals have different samples per datarecord".to_string(),
+                    context().signal(signal as i32)
+                )
+            );
+        }
+        if per_record == 0 || !data.ncols().is_multiple_of(per_record) {
+            return Err(
+                EdfError::InvalidArgument(
+                    format!(
+                        "{} samples is not a multiple of {} samples per datarecord",
+                        data.ncols(),
+                        per_record
+                    ),
+                    context()
+                )
+            );
+        }
+
+        let mut record = self.record_buffer::<f64>();
+        for chunk in data.axis_chunks_iter(Axis(1), per_record) {
+            for (signal, row) in chunk.axis_iter(Axis(0)).enumerate() {
+                for (target, &sample) in record.signal_mut(signal).iter_mut().zip(row.iter()) {
+                    *target = sample;
+                }
+            }
+            self.write_record(&record)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,395 @@
+use std::{ fs, path::PathBuf, time::Duration };
+
+use derive_new::new;
+
+#[cfg(feature = "pure-rust")]
+use crate::native::{ EdfWriter, WriterSpec };
+#[cfg(feature = "ffi")]
+use crate::Edf;
+use crate::{ error::*, header::StartDateTime, info::*, utils::*, AnnotationPosition, Filetype };
+
+/// Configuration of a single signal for [`EdfWriterBuilder`].
+#[derive(new, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalSpec {
+    pub label: String,
+    /// Physical dimension, e.g. `uV`.
+    pub unit: String,
+    /// Samples per second, does not need to be a whole number: the builder picks a datarecord
+    /// duration that holds a whole number of samples of every signal.
+    pub sample_rate: f64,
+    /// Physical `(minimum, maximum)`, the values the ADC range maps to.
+    pub physical_range: (f64, f64),
+    /// Digital `(minimum, maximum)` of the ADC.
+    pub digital_range: (i32, i32),
+    #[new(default)]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub prefilter: String,
+    #[new(default)]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub transducer: String,
+}
+
+impl SignalSpec {
+    pub fn with_prefilter(mut self, prefilter: impl Into<String>) -> Self {
+        self.prefilter = prefilter.into();
+        self
+    }
+
+    pub fn with_transducer(mut self, transducer: impl Into<String>) -> Self {
+        self.transducer = transducer.into();
+        self
+    }
+
+    fn validate(&self, filetype: Filetype, context: impl Fn() -> ErrorContext) -> Result<()> {
+        let invalid = |message: String| Err(EdfError::InvalidArgument(message, context()));
+
+        check_field(&self.label, "label", MAX_LABEL_LEN, &context)?;
+        check_field(&self.unit, "unit", MAX_UNIT_LEN, &context)?;
+        check_field(&self.prefilter, "prefilter", MAX_SIGNAL_FIELD_LEN, &context)?;
+        check_field(&self.transducer, "transducer", MAX_SIGNAL_FIELD_LEN, &context)?;
+
+        if !(self.sample_rate.is_finite() && self.sample_rate > 0.0) {
+            return invalid(format!("sample rate {} must be positive", self.sample_rate));
+        }
+
+        let (phys_min, phys_max) = self.physical_range;
+        check_physical(phys_min, &context)?;
+        check_physical(phys_max, &context)?;
+        if phys_min == phys_max {
+            return Err(EdfError::PhysminIsPhysmax(context()));
+        }
+
+        let (dig_min, dig_max) = self.digital_range;
+        check_digital(filetype, dig_min, &context)?;
+        check_digital(filetype, dig_max, &context)?;
+        if dig_min == dig_max {
+            return Err(EdfError::DigminIsDigmax(context()));
+        }
+        if dig_max < dig_min {
+            return Err(EdfError::DigmaxLowerThanDigmin(context()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Validates a complete writer configuration before anything is written, then opens the file
+/// and applies all settings.
+///
+/// `EdfWriter::to_writer` does not need a path, its builder can start from `default()` with
+/// the type of the file set by [`EdfWriterBuilder::filetype`].
+#[derive(new, Default)]
+pub struct EdfWriterBuilder {
+    path: PathBuf,
+    #[new(default)]
+    filetype: Option<Filetype>,
+    #[new(default)]
+    signals: Vec<SignalSpec>,
+    #[new(default)]
+    patient: Option<PatientInfo>,
+    #[new(default)]
+    recording: Option<RecordingInfo>,
+    #[new(default)]
+    start: Option<StartDateTime>,
+    #[new(default)]
+    datarecord_duration: Option<Duration>,
+    #[new(default)]
+    annotation_signals: Option<usize>,
+    #[new(default)]
+    annotation_position: Option<AnnotationPosition>,
+}
+
+impl EdfWriterBuilder {
+    /// Writes EDF+ or BDF+ regardless of the extension of the path, which picks the type
+    /// otherwise.
+    pub fn filetype(mut self, filetype: Filetype) -> Self {
+        self.filetype = Some(filetype);
+        self
+    }
+
+    pub fn signal(mut self, signal: SignalSpec) -> Self {
+        self.signals.push(signal);
+        self
+    }
+
+    pub fn signals(mut self, signals: impl IntoIterator<Item = SignalSpec>) -> Self {
+        self.signals.extend(signals);
+        self
+    }
+
+    pub fn patient(mut self, patient: PatientInfo) -> Self {
+        self.patient = Some(patient);
+        self
+    }
+
+    pub fn recording(mut self, recording: RecordingInfo) -> Self {
+        self.recording = Some(recording);
+        self
+    }
+
+    /// Start of the recording, the system time at build when not set.
+    pub fn start(mut self, start: StartDateTime) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Start of the recording; out of range dates are reported by [`EdfWriterBuilder::build`].
+    #[cfg(feature = "chrono")]
+    pub fn start_datetime(self, start: chrono::NaiveDateTime) -> Self {
+        self.start(StartDateTime::from_naive(start))
+    }
+
+    /// Uses a fixed datarecord duration instead of picking one from the sample rates. Every
+    /// signal needs a whole number of samples per datarecord.
+    ///
+    /// Without it one second is used if it fits all sample rates, otherwise the shortest longer
+    /// record up to 60 s, e.g. 1.5 s for 2/3 Hz, and only then a shorter one.
+    pub fn datarecord_duration(mut self, duration: Duration) -> Self {
+        self.datarecord_duration = Some(duration);
+        self
+    }
+
+    /// Shorthand for setting only the equipment of the recording info.
+    pub fn equipment(mut self, equipment: impl Into<String>) -> Self {
+        self.recording.get_or_insert_with(Default::default).equipment = equipment.into();
+        self
+    }
+
+    pub fn annotation_signals(mut self, annotation_signals: usize) -> Self {
+        self.annotation_signals = Some(annotation_signals);
+        self
+    }
+
+    pub fn annotation_position(mut self, position: AnnotationPosition) -> Self {
+        self.annotation_position = Some(position);
+        self
+    }
+
+    /// Validates the configuration, opens the file and applies every setting.
+    ///
+    /// If the C library rejects any setting, the file is closed and removed again.
+    ///
+    /// Use [`Edf::sample_frequency`] to get the effective rate of every signal.
+    #[cfg(feature = "ffi")]
+    pub fn build(self) -> Result<Edf> {
+        self.validate()?;
+        let (duration, samples_per_record) = self.datarecord_layout()?;
+
+        let mut edf = Edf::new(self.path.clone());
+        edf.number_of_signals = self.signals.len() as i32;
+        edf.open_file_writeonly_as(self.target_filetype())?;
+
+        if let Err(error) = self.apply(&edf, duration, &samples_per_record) {
+            drop(edf);
+            let _ = fs::remove_file(&self.path);
+            return Err(error);
+        }
+
+        Ok(edf)
+    }
+
+    /// Validates the configuration and creates the file with the native writer, which writes
+    /// the same files as [`EdfWriterBuilder::build`] without the C library.
+    ///
+    /// Without a start the current local time is used, like EDFlib does.
+    #[cfg(feature = "pure-rust")]
+    pub fn build_native(self) -> Result<EdfWriter> {
+        let spec = self.writer_spec()?;
+        let file = fs::File::create(&self.path).map_err(|error| {
+            crate::native::io_error(error, self.context())
+        })?;
+
+        EdfWriter::new(file, spec, Some(self.path.clone())).inspect_err(|_| {
+            let _ = fs::remove_file(&self.path);
+        })
+    }
+
+    #[cfg(feature = "pure-rust")]
+    pub(crate) fn writer_spec(&self) -> Result<WriterSpec> {
+        self.validate()?;
+        let (datarecord_duration, samples_per_record) = self.datarecord_layout()?;
+
+        Ok(WriterSpec {
+            filetype: match self.target_filetype() {
+                Filetype::EDF | Filetype::EDFPLUS => Filetype::EDFPLUS,
+                Filetype::BDF | Filetype::BDFPLUS => Filetype::BDFPLUS,
+            },
+            signals: self.signals.clone(),
+            samples_per_record,
+            datarecord_duration,
+            patient: self.patient.clone().unwrap_or_default(),
+            recording: self.recording.clone().unwrap_or_default(),
+            start: self.start.unwrap_or_else(StartDateTime::now),
+            annotation_signals: self.annotation_signals.unwrap_or(1),
+            annotation_position: self.annotation_position.unwrap_or(AnnotationPosition::End),
+        })
+    }
+
+    fn context(&self) -> ErrorContext {
+        let context = ErrorContext::new("build");
+        if self.path.as_os_str().is_empty() {
+            return context;
+        }
+        context.path(&self.path)
+    }
+
+    fn target_filetype(&self) -> Filetype {
+        self.filetype.unwrap_or_else(|| Filetype::from_path(&self.path))
+    }
+
+    fn validate(&self) -> Result<()> {
+        let filetype = self.target_filetype();
+
+        if self.signals.is_empty() {
+            return Err(EdfError::NoSignals(self.context()));
+        }
+        if self.signals.len() > MAX_SIGNALS {
+            return Err(EdfError::TooManySignals(self.context()));
+        }
+        for (edfsignal, signal) in self.signals.iter().enumerate() {
+            signal.validate(filetype, || self.context().signal(edfsignal as i32))?;
+        }
+        if let Some(patient) = &self.patient {
+            patient.validate(|| self.context())?;
+        }
+        if let Some(recording) = &self.recording {
+            recording.validate(|| self.context())?;
+        }
+        if let Some(start) = &self.start {
+            start.validate(|| self.context())?;
+        }
+
+        Ok(())
+    }
+
+    /// The datarecord duration and the number of samples per datarecord of every signal.
+    fn datarecord_layout(&self) -> Result<(Duration, Vec<i32>)> {
+        let filetype = self.target_filetype();
+        let rates: Vec<f64> = self.signals
+            .iter()
+            .map(|signal| signal.sample_rate)
+            .collect();
+
+        let micros = match self.datarecord_duration {
+            Some(duration) => {
+                let micros = duration.as_micros() as u64;
+                if datarecord_duration_units(duration).is_none() {
+                    return Err(
+                        EdfError::InvalidArgument(
+                            format!("datarecord duration {:?} is not supported", duration),
+                            self.context()
+                        )
+                    );
+                }
+                if samples_per_record(&rates, micros, filetype).is_none() {
+                    return Err(
+                        EdfError::InvalidArgument(
+                            format!(
+                                "datarecord duration {:?} does not fit all sample rates",
+                                duration
+                            ),
+                            self.context()
+                        )
+                    );
+                }
+                micros
+            }
+            None =>
+                datarecord_candidates()
+                    .find(|&micros| samples_per_record(&rates, micros, filetype).is_some())
+                    .ok_or_else(|| {
+                        EdfError::InvalidArgument(
+                            "no datarecord duration fits all sample rates".to_string(),
+                            self.context()
+                        )
+                    })?,
+        };
+
+        let samples = samples_per_record(&rates, micros, filetype).unwrap_or_default();
+        Ok((Duration::from_micros(micros), samples))
+    }
+
+    #[cfg(feature = "ffi")]
+    fn apply(&self, edf: &Edf, duration: Duration, samples_per_record: &[i32]) -> Result<()> {
+        if duration != Duration::from_secs(1) {
+            edf.set_datarecord_duration(duration)?;
+        }
+
+        for (edfsignal, signal) in self.signals.iter().enumerate() {
+            let samples = samples_per_record[edfsignal];
+            let edfsignal = edfsignal as i32;
+            edf.set_samplefrequency(edfsignal, samples)?;
+            edf.set_physical_minimum(edfsignal, signal.physical_range.0)?;
+            edf.set_physical_maximum(edfsignal, signal.physical_range.1)?;
+            edf.set_digital_minimum(edfsignal, signal.digital_range.0)?;
+            edf.set_digital_maximum(edfsignal, signal.digital_range.1)?;
+            edf.set_label(edfsignal, &signal.label)?;
+            edf.set_physical_dimension(edfsignal, &signal.unit)?;
+            if !signal.prefilter.is_empty() {
+                edf.set_prefilter(edfsignal, &signal.prefilter)?;
+            }
+            if !signal.transducer.is_empty() {
+                edf.set_transducer(edfsignal, &signal.transducer)?;
+            }
+        }
+
+        if let Some(patient) = &self.patient {
+            edf.set_patient_info(patient)?;
+        }
+        if let Some(recording) = &self.recording {
+            edf.set_recording_info(recording)?;
+        }
+        if let Some(start) = &self.start {
+            edf.set_startdatetime(start)?;
+        }
+        if let Some(annotation_signals) = self.annotation_signals {
+            edf.set_number_of_annotation_signals(annotation_signals)?;
+        }
+        if let Some(position) = self.annotation_position {
+            edf.set_annot_chan_idx_pos(position)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// EDFlib refuses datarecords larger than 10 MB.
+const MAX_DATARECORD_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Datarecord durations in microseconds in order of preference: one second, then the shortest
+/// longer record up to 60 s in steps of 10 us, then shorter records in steps of 10 us, then
+/// micro-second records below 1 ms for very high sample rates.
+fn datarecord_candidates() -> impl Iterator<Item = u64> {
+    let longer = (100_000..=6_000_000).map(|d| d * 10);
+    let sub_second = (100..100_000).rev().map(|d| d * 10);
+    let micro = (1..1_000).rev();
+    longer.chain(sub_second).chain(micro)
+}
+
+/// Samples per datarecord of every signal for a datarecord of `micros`, or `None` if one of
+/// them is not a whole number or the datarecord gets too big.
+fn samples_per_record(rates: &[f64], micros: u64, filetype: Filetype) -> Option<Vec<i32>> {
+    let seconds = (micros as f64) / 1_000_000.0;
+    let samples = rates
+        .iter()
+        .map(|rate| {
+            let samples = rate * seconds;
+            let rounded = samples.round();
+            // only rounding errors, with 10 us steps above a second a looser bound lets
+            // close misses through, e.g. 2501.0001 samples of 2500.5 Hz in 1.0002 s
+            let integral = (samples - rounded).abs() <= 1e-9 * rounded.max(1.0);
+            (integral && rounded >= 1.0 && rounded <= (i32::MAX as f64)).then_some(rounded as i32)
+        })
+        .collect::<Option<Vec<i32>>>()?;
+
+    let bytes_per_sample = match filetype {
+        Filetype::EDF | Filetype::EDFPLUS => 2,
+        Filetype::BDF | Filetype::BDFPLUS => 3,
+    };
+    let size: u64 = samples
+        .iter()
+        .map(|&samples| (samples as u64) * bytes_per_sample)
+        .sum();
+    (size <= MAX_DATARECORD_SIZE).then_some(samples)
+}
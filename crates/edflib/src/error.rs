@@ -0,0 +1,277 @@
+use std::{ error, fmt, os::raw::c_int, path::{ Path, PathBuf }, time::Duration };
+
+pub type Result<T> = std::result::Result<T, EdfError>;
+
+// The error codes of edflib.h, kept here so errors do not depend on the C bindings.
+const EDFLIB_MALLOC_ERROR: c_int = -1;
+const EDFLIB_NO_SUCH_FILE_OR_DIRECTORY: c_int = -2;
+const EDFLIB_FILE_CONTAINS_FORMAT_ERRORS: c_int = -3;
+const EDFLIB_MAXFILES_REACHED: c_int = -4;
+const EDFLIB_FILE_READ_ERROR: c_int = -5;
+const EDFLIB_FILE_ALREADY_OPENED: c_int = -6;
+const EDFLIB_FILETYPE_ERROR: c_int = -7;
+const EDFLIB_FILE_WRITE_ERROR: c_int = -8;
+const EDFLIB_NUMBER_OF_SIGNALS_INVALID: c_int = -9;
+const EDFLIB_FILE_IS_DISCONTINUOUS: c_int = -10;
+const EDFLIB_INVALID_READ_ANNOTS_VALUE: c_int = -11;
+const EDFLIB_ARCH_ERROR: c_int = -12;
+const EDFLIB_NO_SIGNALS: c_int = -20;
+const EDFLIB_TOO_MANY_SIGNALS: c_int = -21;
+const EDFLIB_NO_SAMPLES_IN_RECORD: c_int = -22;
+const EDFLIB_DIGMIN_IS_DIGMAX: c_int = -23;
+const EDFLIB_DIGMAX_LOWER_THAN_DIGMIN: c_int = -24;
+const EDFLIB_PHYSMIN_IS_PHYSMAX: c_int = -25;
+const EDFLIB_DATARECORD_SIZE_TOO_BIG: c_int = -26;
+
+/// Where an error happened: the failing operation and, when known, the signal and file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub operation: &'static str,
+    pub signal: Option<i32>,
+    pub path: Option<PathBuf>,
+}
+
+impl ErrorContext {
+    pub(crate) fn new(operation: &'static str) -> Self {
+        ErrorContext { operation, signal: None, path: None }
+    }
+
+    pub(crate) fn signal(mut self, signal: i32) -> Self {
+        self.signal = Some(signal);
+        self
+    }
+
+    pub(crate) fn path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(signal) = self.signal {
+            write!(f, " (signal {})", signal)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, " on \"{}\"", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors returned by the reader and writer APIs.
+///
+/// Every `EDFLIB_*` error code of the C library has its own variant, so failures can be matched
+/// on programmatically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdfError {
+    /// `EDFLIB_MALLOC_ERROR`
+    MallocError(ErrorContext),
+    /// `EDFLIB_NO_SUCH_FILE_OR_DIRECTORY`
+    NoSuchFileOrDirectory(ErrorContext),
+    /// `EDFLIB_FILE_CONTAINS_FORMAT_ERRORS`
+    FileContainsFormatErrors(ErrorContext),
+    /// `EDFLIB_MAXFILES_REACHED`
+    MaxfilesReached(ErrorContext),
+    /// `EDFLIB_FILE_READ_ERROR`
+    FileReadError(ErrorContext),
+    /// `EDFLIB_FILE_ALREADY_OPENED`
+    FileAlreadyOpened(ErrorContext),
+    /// `EDFLIB_FILETYPE_ERROR`
+    FiletypeError(ErrorContext),
+    /// `EDFLIB_FILE_WRITE_ERROR`
+    FileWriteError(ErrorContext),
+    /// `EDFLIB_NUMBER_OF_SIGNALS_INVALID`
+    NumberOfSignalsInvalid(ErrorContext),
+    /// `EDFLIB_FILE_IS_DISCONTINUOUS`
+    FileIsDiscontinuous(ErrorContext),
+    /// `EDFLIB_INVALID_READ_ANNOTS_VALUE`
+    InvalidReadAnnotsValue(ErrorContext),
+    /// `EDFLIB_ARCH_ERROR`
+    ArchError(ErrorContext),
+    /// `EDFLIB_NO_SIGNALS`
+    NoSignals(ErrorContext),
+    /// `EDFLIB_TOO_MANY_SIGNALS`
+    TooManySignals(ErrorContext),
+    /// `EDFLIB_NO_SAMPLES_IN_RECORD`
+    NoSamplesInRecord(ErrorContext),
+    /// `EDFLIB_DIGMIN_IS_DIGMAX`
+    DigminIsDigmax(ErrorContext),
+    /// `EDFLIB_DIGMAX_LOWER_THAN_DIGMIN`
+    DigmaxLowerThanDigmin(ErrorContext),
+    /// `EDFLIB_PHYSMIN_IS_PHYSMAX`
+    PhysminIsPhysmax(ErrorContext),
+    /// `EDFLIB_DATARECORD_SIZE_TOO_BIG`
+    DatarecordSizeTooBig(ErrorContext),
+    /// The backend rejected the call, for EDFlib a plain `-1`, e.g. an out of range argument
+    /// or a setter called after the first sample was written.
+    Rejected(ErrorContext),
+    /// The path can not be passed to the C library.
+    InvalidPath(ErrorContext),
+    /// A string argument contains a NUL character.
+    InteriorNul(ErrorContext),
+    /// A string argument contains characters outside of Latin-1.
+    NotLatin1(ErrorContext),
+    /// An argument was rejected before reaching the C library.
+    InvalidArgument(String, ErrorContext),
+    /// An annotation starts before the recording, which [`crate::Annotation::onset`] can not
+    /// hold. The onset is in units of 100 ns.
+    NegativeOnset(i64, ErrorContext),
+    /// A negative return value that does not match any known `EDFLIB_*` code.
+    Unknown(c_int, ErrorContext),
+}
+
+impl EdfError {
+    /// Maps a negative `EDFLIB_*` code to its variant.
+    #[cfg(feature = "ffi")]
+    pub(crate) fn from_code(code: c_int, context: ErrorContext) -> Self {
+        match code {
+            EDFLIB_MALLOC_ERROR => EdfError::MallocError(context),
+            EDFLIB_NO_SUCH_FILE_OR_DIRECTORY => EdfError::NoSuchFileOrDirectory(context),
+            EDFLIB_FILE_CONTAINS_FORMAT_ERRORS => EdfError::FileContainsFormatErrors(context),
+            EDFLIB_MAXFILES_REACHED => EdfError::MaxfilesReached(context),
+            EDFLIB_FILE_READ_ERROR => EdfError::FileReadError(context),
+            EDFLIB_FILE_ALREADY_OPENED => EdfError::FileAlreadyOpened(context),
+            EDFLIB_FILETYPE_ERROR => EdfError::FiletypeError(context),
+            EDFLIB_FILE_WRITE_ERROR => EdfError::FileWriteError(context),
+            EDFLIB_NUMBER_OF_SIGNALS_INVALID => EdfError::NumberOfSignalsInvalid(context),
+            EDFLIB_FILE_IS_DISCONTINUOUS => EdfError::FileIsDiscontinuous(context),
+            EDFLIB_INVALID_READ_ANNOTS_VALUE => EdfError::InvalidReadAnnotsValue(context),
+            EDFLIB_ARCH_ERROR => EdfError::ArchError(context),
+            EDFLIB_NO_SIGNALS => EdfError::NoSignals(context),
+            EDFLIB_TOO_MANY_SIGNALS => EdfError::TooManySignals(context),
+            EDFLIB_NO_SAMPLES_IN_RECORD => EdfError::NoSamplesInRecord(context),
+            EDFLIB_DIGMIN_IS_DIGMAX => EdfError::DigminIsDigmax(context),
+            EDFLIB_DIGMAX_LOWER_THAN_DIGMIN => EdfError::DigmaxLowerThanDigmin(context),
+            EDFLIB_PHYSMIN_IS_PHYSMAX => EdfError::PhysminIsPhysmax(context),
+            EDFLIB_DATARECORD_SIZE_TOO_BIG => EdfError::DatarecordSizeTooBig(context),
+            code => EdfError::Unknown(code, context),
+        }
+    }
+
+    /// Maps the result of a call that returns `-1` on generic failure and an `EDFLIB_*` code
+    /// otherwise, e.g. the sample write functions.
+    #[cfg(feature = "ffi")]
+    pub(crate) fn check(result: c_int, context: impl FnOnce() -> ErrorContext) -> Result<c_int> {
+        match result {
+            result if result >= 0 => Ok(result),
+            -1 => Err(EdfError::Rejected(context())),
+            code => Err(EdfError::from_code(code, context())),
+        }
+    }
+
+    /// The `EDFLIB_*` code this error corresponds to, if any.
+    pub fn code(&self) -> Option<c_int> {
+        match self {
+            EdfError::MallocError(_) => Some(EDFLIB_MALLOC_ERROR),
+            EdfError::NoSuchFileOrDirectory(_) => Some(EDFLIB_NO_SUCH_FILE_OR_DIRECTORY),
+            EdfError::FileContainsFormatErrors(_) => Some(EDFLIB_FILE_CONTAINS_FORMAT_ERRORS),
+            EdfError::MaxfilesReached(_) => Some(EDFLIB_MAXFILES_REACHED),
+            EdfError::FileReadError(_) => Some(EDFLIB_FILE_READ_ERROR),
+            EdfError::FileAlreadyOpened(_) => Some(EDFLIB_FILE_ALREADY_OPENED),
+            EdfError::FiletypeError(_) => Some(EDFLIB_FILETYPE_ERROR),
+            EdfError::FileWriteError(_) => Some(EDFLIB_FILE_WRITE_ERROR),
+            EdfError::NumberOfSignalsInvalid(_) => Some(EDFLIB_NUMBER_OF_SIGNALS_INVALID),
+            EdfError::FileIsDiscontinuous(_) => Some(EDFLIB_FILE_IS_DISCONTINUOUS),
+            EdfError::InvalidReadAnnotsValue(_) => Some(EDFLIB_INVALID_READ_ANNOTS_VALUE),
+            EdfError::ArchError(_) => Some(EDFLIB_ARCH_ERROR),
+            EdfError::NoSignals(_) => Some(EDFLIB_NO_SIGNALS),
+            EdfError::TooManySignals(_) => Some(EDFLIB_TOO_MANY_SIGNALS),
+            EdfError::NoSamplesInRecord(_) => Some(EDFLIB_NO_SAMPLES_IN_RECORD),
+            EdfError::DigminIsDigmax(_) => Some(EDFLIB_DIGMIN_IS_DIGMAX),
+            EdfError::DigmaxLowerThanDigmin(_) => Some(EDFLIB_DIGMAX_LOWER_THAN_DIGMIN),
+            EdfError::PhysminIsPhysmax(_) => Some(EDFLIB_PHYSMIN_IS_PHYSMAX),
+            EdfError::DatarecordSizeTooBig(_) => Some(EDFLIB_DATARECORD_SIZE_TOO_BIG),
+            EdfError::Unknown(code, _) => Some(*code),
+            EdfError::Rejected(_) |
+            EdfError::InvalidPath(_) |
+            EdfError::InteriorNul(_) |
+            EdfError::NotLatin1(_) |
+            EdfError::InvalidArgument(_, _) |
+            EdfError::NegativeOnset(_, _) => None,
+        }
+    }
+
+    pub fn context(&self) -> &ErrorContext {
+        match self {
+            EdfError::MallocError(context) |
+            EdfError::NoSuchFileOrDirectory(context) |
+            EdfError::FileContainsFormatErrors(context) |
+            EdfError::MaxfilesReached(context) |
+            EdfError::FileReadError(context) |
+            EdfError::FileAlreadyOpened(context) |
+            EdfError::FiletypeError(context) |
+            EdfError::FileWriteError(context) |
+            EdfError::NumberOfSignalsInvalid(context) |
+            EdfError::FileIsDiscontinuous(context) |
+            EdfError::InvalidReadAnnotsValue(context) |
+            EdfError::ArchError(context) |
+            EdfError::NoSignals(context) |
+            EdfError::TooManySignals(context) |
+            EdfError::NoSamplesInRecord(context) |
+            EdfError::DigminIsDigmax(context) |
+            EdfError::DigmaxLowerThanDigmin(context) |
+            EdfError::PhysminIsPhysmax(context) |
+            EdfError::DatarecordSizeTooBig(context) |
+            EdfError::Rejected(context) |
+            EdfError::InvalidPath(context) |
+            EdfError::InteriorNul(context) |
+            EdfError::NotLatin1(context) |
+            EdfError::InvalidArgument(_, context) |
+            EdfError::NegativeOnset(_, context) |
+            EdfError::Unknown(_, context) => context,
+        }
+    }
+
+    fn description(&self) -> String {
+        (
+            match self {
+                EdfError::MallocError(_) => "memory allocation error",
+                EdfError::NoSuchFileOrDirectory(_) => "no such file or directory",
+                EdfError::FileContainsFormatErrors(_) =>
+                    "the file is not EDF(+) or BDF(+) compliant (it contains format errors)",
+                EdfError::MaxfilesReached(_) => "too many files opened",
+                EdfError::FileReadError(_) => "a read error occurred",
+                EdfError::FileAlreadyOpened(_) => "file has already been opened",
+                EdfError::FiletypeError(_) => "invalid filetype",
+                EdfError::FileWriteError(_) => "a write error occurred",
+                EdfError::NumberOfSignalsInvalid(_) => "invalid number of signals",
+                EdfError::FileIsDiscontinuous(_) =>
+                    "the file is discontinuous (EDF+D/BDF+D) and can not be read",
+                EdfError::InvalidReadAnnotsValue(_) => "invalid annotation reading mode",
+                EdfError::ArchError(_) => "unsupported architecture",
+                EdfError::NoSignals(_) => "no signals",
+                EdfError::TooManySignals(_) => "too many signals",
+                EdfError::NoSamplesInRecord(_) => "a signal has no samples in the datarecord",
+                EdfError::DigminIsDigmax(_) => "digital minimum equals digital maximum",
+                EdfError::DigmaxLowerThanDigmin(_) =>
+                    "digital maximum is lower than digital minimum",
+                EdfError::PhysminIsPhysmax(_) => "physical minimum equals physical maximum",
+                EdfError::DatarecordSizeTooBig(_) => "datarecord size is too big",
+                EdfError::Rejected(_) => "the call was rejected",
+                EdfError::InvalidPath(_) => "path is not valid UTF-8 or contains a NUL character",
+                EdfError::InteriorNul(_) => "string contains a NUL character",
+                EdfError::NotLatin1(_) => "string contains characters outside of Latin-1",
+                EdfError::InvalidArgument(message, _) => {
+                    return message.clone();
+                }
+                EdfError::NegativeOnset(ticks, _) => {
+                    let before = Duration::from_nanos(ticks.unsigned_abs().saturating_mul(100));
+                    return format!("the annotation starts {:?} before the recording", before);
+                }
+                EdfError::Unknown(code, _) => {
+                    return format!("unknown error code {}", code);
+                }
+            }
+        ).to_string()
+    }
+}
+
+impl fmt::Display for EdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.context(), self.description())
+    }
+}
+
+impl error::Error for EdfError {}
@@ -0,0 +1,39 @@
+//! Calls into EDFlib that touch its global state.
+
+use std::{ os::raw::{ c_char, c_int }, sync::{ Mutex, MutexGuard, PoisonError } };
+
+use edflib_sys::*;
+
+/// EDFlib keeps its table of open files and their count in globals without any locking, every
+/// open and close of a reader or a writer holds this lock.
+static OPEN_FILES: Mutex<()> = Mutex::new(());
+
+fn lock() -> MutexGuard<'static, ()> {
+    OPEN_FILES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// `edfopen_file_readonly` under the lock of the open files.
+pub(crate) unsafe fn open_file_readonly(
+    path: *const c_char,
+    hdr: *mut edflib_hdr_t,
+    read_annotations: c_int
+) -> c_int {
+    let _open_files = lock();
+    edfopen_file_readonly(path, hdr, read_annotations)
+}
+
+/// `edfopen_file_writeonly` under the lock of the open files.
+pub(crate) unsafe fn open_file_writeonly(
+    path: *const c_char,
+    filetype: c_int,
+    number_of_signals: c_int
+) -> c_int {
+    let _open_files = lock();
+    edfopen_file_writeonly(path, filetype, number_of_signals)
+}
+
+/// `edfclose_file` under the lock of the open files.
+pub(crate) unsafe fn close_file(handle: c_int) -> c_int {
+    let _open_files = lock();
+    edfclose_file(handle)
+}
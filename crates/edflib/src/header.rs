@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+#[cfg(feature = "chrono")]
+use chrono::{ Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike };
+#[cfg(feature = "ffi")]
+use edflib_sys::*;
+
+use crate::{ error::*, info::*, Filetype };
+#[cfg(feature = "ffi")]
+use crate::utils::*;
+
+/// Parameters of a single (non-annotation) signal as stored in the file header.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalInfo {
+    pub label: String,
+    pub physical_dimension: String,
+    pub prefilter: String,
+    pub transducer: String,
+    pub physical_maximum: f64,
+    pub physical_minimum: f64,
+    pub digital_maximum: i32,
+    pub digital_minimum: i32,
+    pub samples_in_file: i64,
+    pub samples_in_datarecord: i32,
+}
+
+#[cfg(feature = "ffi")]
+impl SignalInfo {
+    fn from_raw(param: &edflib_param_t) -> Self {
+        SignalInfo {
+            label: chars_to_string(&param.label),
+            physical_dimension: chars_to_string(&param.physdimension),
+            prefilter: chars_to_string(&param.prefilter),
+            transducer: chars_to_string(&param.transducer),
+            physical_maximum: param.phys_max,
+            physical_minimum: param.phys_min,
+            digital_maximum: param.dig_max,
+            digital_minimum: param.dig_min,
+            samples_in_file: param.smp_in_file,
+            samples_in_datarecord: param.smp_in_datarecord,
+        }
+    }
+}
+
+/// Start date and time of the recording as found in the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StartDateTime {
+    /// 1985 - 2084
+    pub year: i32,
+    pub month: i32,
+    pub day: i32,
+    pub hour: i32,
+    pub minute: i32,
+    pub second: i32,
+    /// Only used by EDF+ and BDF+, always less than one second. Stored with a resolution of
+    /// 100 nanoseconds, anything finer is truncated when writing.
+    pub subsecond: Duration,
+}
+
+impl StartDateTime {
+    /// Checks the ranges EDFlib accepts, including the 1985 - 2084 year window of the
+    /// two-digit header date.
+    pub fn validate(&self, context: impl Fn() -> ErrorContext) -> Result<()> {
+        let valid =
+            (1985..=2084).contains(&self.year) &&
+            (1..=12).contains(&self.month) &&
+            (1..=31).contains(&self.day) &&
+            (0..=23).contains(&self.hour) &&
+            (0..=59).contains(&self.minute) &&
+            (0..=59).contains(&self.second) &&
+            self.subsecond < Duration::from_secs(1);
+        if !valid {
+            return Err(
+                EdfError::InvalidArgument(format!("invalid start date/time {:?}", self), context())
+            );
+        }
+        Ok(())
+    }
+
+    /// The subsecond in units of 100 nanoseconds.
+    #[cfg(feature = "ffi")]
+    pub(crate) fn subsecond_ticks(&self) -> i32 {
+        (self.subsecond.subsec_nanos() / 100) as i32
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl StartDateTime {
+    pub fn to_naive(&self) -> Option<NaiveDateTime> {
+        let date = NaiveDate::from_ymd_opt(self.year, self.month as u32, self.day as u32)?;
+        let time = NaiveTime::from_hms_nano_opt(
+            self.hour as u32,
+            self.minute as u32,
+            self.second as u32,
+            self.subsecond.subsec_nanos()
+        )?;
+        Some(NaiveDateTime::new(date, time))
+    }
+
+    /// Converts without validating, truncating to 100 nanoseconds.
+    pub(crate) fn from_naive(datetime: NaiveDateTime) -> Self {
+        // leap seconds are stored by chrono as nanoseconds past 1_000_000_000
+        let nanos = datetime.nanosecond().min(999_999_999);
+        StartDateTime {
+            year: datetime.year(),
+            month: datetime.month() as i32,
+            day: datetime.day() as i32,
+            hour: datetime.hour() as i32,
+            minute: datetime.minute() as i32,
+            second: datetime.second() as i32,
+            subsecond: Duration::from_nanos(((nanos / 100) * 100) as u64),
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<NaiveDateTime> for StartDateTime {
+    type Error = EdfError;
+
+    fn try_from(datetime: NaiveDateTime) -> Result<Self> {
+        let start = StartDateTime::from_naive(datetime);
+        start.validate(|| ErrorContext::new("StartDateTime::try_from"))?;
+        Ok(start)
+    }
+}
+
+/// Owned copy of the general header of an opened file.
+///
+/// Patient and recording fields follow the C library: `patient` and `recording` are only set
+/// for plain EDF/BDF, the split fields only for EDF+/BDF+.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    pub filetype: Filetype,
+    pub file_duration: Duration,
+    pub start: StartDateTime,
+    pub patient: String,
+    pub recording: String,
+    pub patientcode: String,
+    pub sex: String,
+    pub birthdate: String,
+    pub birthdate_day: i32,
+    pub birthdate_month: i32,
+    pub birthdate_year: i32,
+    pub patient_name: String,
+    pub patient_additional: String,
+    pub admincode: String,
+    pub technician: String,
+    pub equipment: String,
+    pub recording_additional: String,
+    pub datarecord_duration: Duration,
+    pub datarecords_in_file: i64,
+    pub annotations_in_file: i64,
+    pub signals: Vec<SignalInfo>,
+}
+
+impl Header {
+    #[cfg(feature = "ffi")]
+    pub(crate) fn from_raw(hdr: &edflib_hdr_t, filetype: Filetype) -> Self {
+        let signals = hdr.signalparam
+            .iter()
+            .take(hdr.edfsignals.max(0) as usize)
+            .map(SignalInfo::from_raw)
+            .collect();
+
+        Header {
+            filetype,
+            file_duration: ticks_to_duration(hdr.file_duration),
+            start: StartDateTime {
+                year: hdr.startdate_year,
+                month: hdr.startdate_month,
+                day: hdr.startdate_day,
+                hour: hdr.starttime_hour,
+                minute: hdr.starttime_minute,
+                second: hdr.starttime_second,
+                subsecond: ticks_to_duration(hdr.starttime_subsecond),
+            },
+            patient: chars_to_string(&hdr.patient),
+            recording: chars_to_string(&hdr.recording),
+            patientcode: chars_to_string(&hdr.patientcode),
+            sex: chars_to_string(&hdr.sex),
+            birthdate: chars_to_string(&hdr.birthdate),
+            birthdate_day: hdr.birthdate_day,
+            birthdate_month: hdr.birthdate_month,
+            birthdate_year: hdr.birthdate_year,
+            patient_name: chars_to_string(&hdr.patient_name),
+            patient_additional: chars_to_string(&hdr.patient_additional),
+            admincode: chars_to_string(&hdr.admincode),
+            technician: chars_to_string(&hdr.technician),
+            equipment: chars_to_string(&hdr.equipment),
+            recording_additional: chars_to_string(&hdr.recording_additional),
+            datarecord_duration: ticks_to_duration(hdr.datarecord_duration),
+            datarecords_in_file: hdr.datarecords_in_file,
+            annotations_in_file: hdr.annotations_in_file,
+            signals,
+        }
+    }
+
+    /// The EDF+ patient subfields, empty for plain EDF/BDF.
+    pub fn patient_info(&self) -> PatientInfo {
+        PatientInfo::from_header(self)
+    }
+
+    /// The EDF+ recording subfields, empty for plain EDF/BDF.
+    pub fn recording_info(&self) -> RecordingInfo {
+        RecordingInfo::from_header(self)
+    }
+
+    /// The start of the recording with 100 nanosecond precision.
+    #[cfg(feature = "chrono")]
+    pub fn start_datetime(&self) -> Option<NaiveDateTime> {
+        self.start.to_naive()
+    }
+
+    /// Effective sample frequency of `signal` in Hz, taking the datarecord duration into account.
+    pub fn sample_frequency(&self, signal: usize) -> Option<f64> {
+        let signal = self.signals.get(signal)?;
+        Some((signal.samples_in_datarecord as f64) / self.datarecord_duration.as_secs_f64())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{ error::*, header::Header, utils::* };
+
+/// Length of the EDF+ local patient and local recording identification fields.
+pub const MAX_IDENTIFICATION_LEN: usize = 80;
+/// Characters EDFlib keeps for code, name and additional of the patient identification.
+const MAX_PATIENT_LEN: usize = 72;
+/// Characters EDFlib keeps for code, name and additional of the patient identification when a
+/// birthdate takes up the rest.
+const MAX_PATIENT_LEN_WITH_BIRTHDATE: usize = 62;
+/// Characters EDFlib keeps for the recording identification after the start date.
+const MAX_RECORDING_LEN: usize = 52;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+impl Sex {
+    #[cfg(feature = "ffi")]
+    pub(crate) fn to_raw(self) -> i32 {
+        match self {
+            Sex::Female => 0,
+            Sex::Male => 1,
+        }
+    }
+
+    fn from_header(sex: &str) -> Option<Self> {
+        match sex {
+            "Female" | "F" => Some(Sex::Female),
+            "Male" | "M" => Some(Sex::Male),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Birthdate {
+    /// 1800 - 3000
+    pub year: i32,
+    /// 1 - 12
+    pub month: i32,
+    /// 1 - 31
+    pub day: i32,
+}
+
+impl Birthdate {
+    pub(crate) fn validate(&self, context: impl Fn() -> ErrorContext) -> Result<()> {
+        if
+            !(1800..=3000).contains(&self.year) ||
+            !(1..=12).contains(&self.month) ||
+            !(1..=31).contains(&self.day)
+        {
+            return Err(
+                EdfError::InvalidArgument(format!("invalid birthdate {:?}", self), context())
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Subfields of the EDF+ local patient identification.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatientInfo {
+    pub code: String,
+    pub sex: Option<Sex>,
+    pub birthdate: Option<Birthdate>,
+    pub name: String,
+    pub additional: String,
+}
+
+impl PatientInfo {
+    pub(crate) fn from_header(header: &Header) -> Self {
+        let birthdate = Birthdate {
+            year: header.birthdate_year,
+            month: header.birthdate_month,
+            day: header.birthdate_day,
+        };
+
+        PatientInfo {
+            code: header.patientcode.clone(),
+            sex: Sex::from_header(&header.sex),
+            birthdate: if birthdate.year > 0 { Some(birthdate) } else { None },
+            name: header.patient_name.clone(),
+            additional: header.patient_additional.clone(),
+        }
+    }
+
+    /// Checks the character set of every subfield and that nothing is cut off when the
+    /// identification is written. EDFlib keeps 72 characters for code, name and additional,
+    /// separated by one more space, and 62 with a birthdate; spaces around a subfield don't
+    /// count.
+    pub fn validate(&self, context: impl Fn() -> ErrorContext) -> Result<()> {
+        check_ascii_field(&self.code, "patient code", MAX_IDENTIFICATION_LEN, &context)?;
+        check_ascii_field(&self.name, "patient name", MAX_IDENTIFICATION_LEN, &context)?;
+        check_ascii_field(
+            &self.additional,
+            "patient additional",
+            MAX_IDENTIFICATION_LEN,
+            &context
+        )?;
+        if let Some(birthdate) = &self.birthdate {
+            birthdate.validate(&context)?;
+        }
+
+        // sex and birthdate have their own place, the separator before additional takes one
+        let additional = match trimmed_len(&self.additional) {
+            0 => 0,
+            len => 1 + len,
+        };
+        let len = trimmed_len(&self.code) + trimmed_len(&self.name) + additional;
+        let max = if self.birthdate.is_some() {
+            MAX_PATIENT_LEN_WITH_BIRTHDATE
+        } else {
+            MAX_PATIENT_LEN
+        };
+        check_identification_len(len, max, "patient identification", &context)
+    }
+}
+
+/// Subfields of the EDF+ local recording identification, the startdate is set separately.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordingInfo {
+    pub admincode: String,
+    pub technician: String,
+    pub equipment: String,
+    pub additional: String,
+}
+
+impl RecordingInfo {
+    pub(crate) fn from_header(header: &Header) -> Self {
+        RecordingInfo {
+            admincode: header.admincode.clone(),
+            technician: header.technician.clone(),
+            equipment: header.equipment.clone(),
+            additional: header.recording_additional.clone(),
+        }
+    }
+
+    /// Checks the character set of every subfield and that nothing is cut off when the
+    /// identification is written. EDFlib keeps 52 characters after the start date for all
+    /// subfields together, spaces around a subfield don't count.
+    pub fn validate(&self, context: impl Fn() -> ErrorContext) -> Result<()> {
+        check_ascii_field(&self.admincode, "admincode", MAX_IDENTIFICATION_LEN, &context)?;
+        check_ascii_field(&self.technician, "technician", MAX_IDENTIFICATION_LEN, &context)?;
+        check_ascii_field(&self.equipment, "equipment", MAX_IDENTIFICATION_LEN, &context)?;
+        check_ascii_field(
+            &self.additional,
+            "recording additional",
+            MAX_IDENTIFICATION_LEN,
+            &context
+        )?;
+
+        let len =
+            trimmed_len(&self.admincode) +
+            trimmed_len(&self.technician) +
+            trimmed_len(&self.equipment) +
+            trimmed_len(&self.additional);
+        check_identification_len(len, MAX_RECORDING_LEN, "recording identification", &context)
+    }
+}
+
+/// Length of a subfield as EDFlib writes it, without the spaces around it.
+fn trimmed_len(value: &str) -> usize {
+    value.trim_matches(' ').len()
+}
+
+fn check_identification_len(
+    len: usize,
+    max: usize,
+    name: &str,
+    context: impl Fn() -> ErrorContext
+) -> Result<()> {
+    if len > max {
+        return Err(
+            EdfError::InvalidArgument(
+                format!("{} has {} characters, at most {} fit", name, len, max),
+                context()
+            )
+        );
+    }
+    Ok(())
+}
@@ -1,15 +1,67 @@
-use std::{ os::raw::c_int, path::PathBuf, sync::{ Arc, Mutex } };
-
-use anyhow::{ anyhow, Result };
+use std::path::Path;
+#[cfg(feature = "ffi")]
+use std::{
+    os::raw::{ c_char, c_int, c_short },
+    path::PathBuf,
+    sync::{ Arc, Mutex },
+    time::Duration,
+};
+
+#[cfg(feature = "ffi")]
 use derive_new::new;
+#[cfg(feature = "ffi")]
 use edflib_sys::*;
+#[cfg(feature = "ffi")]
 use utils::*;
 
+#[cfg(not(any(feature = "ffi", feature = "pure-rust")))]
+compile_error!("enable the `ffi` or the `pure-rust` feature to get a backend");
+
+pub use annotation::{ Annotation, Annotations, ReadAnnotations };
+pub use builder::{ EdfWriterBuilder, SignalSpec };
+pub use error::{ EdfError, ErrorContext, Result };
+pub use header::{ Header, SignalInfo, StartDateTime };
+pub use info::{ Birthdate, PatientInfo, RecordingInfo, Sex };
+#[cfg(feature = "pure-rust")]
+pub use native::EdfWriter;
+#[cfg(feature = "mmap")]
+pub use native::{ Datarecord, MappedEdf };
+pub use reader::EdfReader;
+#[cfg(feature = "ffi")]
+pub use record::{ RecordBuffer, RecordSample };
+pub use signal::{ Chunks, SignalReader };
+#[cfg(feature = "ffi")]
+pub use streaming::StreamingWriter;
+pub use window::Window;
+
+mod annotation;
+#[cfg(feature = "ndarray")]
+mod array;
+mod builder;
+mod error;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod header;
+mod info;
+#[cfg(feature = "pure-rust")]
+mod native;
+mod reader;
+#[cfg(feature = "ffi")]
+mod record;
+mod signal;
+#[cfg(feature = "ffi")]
+mod streaming;
+#[cfg_attr(not(feature = "ffi"), allow(dead_code))]
 mod utils;
+mod window;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Filetype {
     EDF,
+    EDFPLUS,
     BDF,
+    BDFPLUS,
 }
 
 impl Filetype {
@@ -21,23 +73,53 @@ impl Filetype {
         }
     }
 
+    fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        Filetype::from(ext)
+    }
+
+    #[cfg(feature = "ffi")]
+    fn from_raw(filetype: c_int) -> Option<Self> {
+        match filetype as u32 {
+            EDFLIB_FILETYPE_EDF => Some(Filetype::EDF),
+            EDFLIB_FILETYPE_EDFPLUS => Some(Filetype::EDFPLUS),
+            EDFLIB_FILETYPE_BDF => Some(Filetype::BDF),
+            EDFLIB_FILETYPE_BDFPLUS => Some(Filetype::BDFPLUS),
+            _ => None,
+        }
+    }
+
+    /// Lowest and highest digital value a sample can take: 16 bit for EDF, 24 bit for BDF.
+    pub fn digital_limits(&self) -> (i32, i32) {
+        match self {
+            Filetype::EDF | Filetype::EDFPLUS => (-32768, 32767),
+            Filetype::BDF | Filetype::BDFPLUS => (-8388608, 8388607),
+        }
+    }
+
     #[allow(unused)]
     fn as_str(&self) -> &str {
         match self {
-            Filetype::EDF => "edf",
-            Filetype::BDF => "bdf",
+            Filetype::EDF | Filetype::EDFPLUS => "edf",
+            Filetype::BDF | Filetype::BDFPLUS => "bdf",
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AnnotationPosition {
     Start,
     Middle,
     End,
 }
 
+#[cfg(feature = "ffi")]
 impl AnnotationPosition {
-    fn to_raw(&self) -> i32 {
+    fn to_raw(self) -> i32 {
         (match self {
             AnnotationPosition::Start => EDF_ANNOT_IDX_POS_START,
             AnnotationPosition::Middle => EDF_ANNOT_IDX_POS_MIDDLE,
@@ -46,15 +128,23 @@ impl AnnotationPosition {
     }
 }
 
+#[cfg(feature = "ffi")]
 #[derive(new)]
 struct Inner {
-    #[new(value = "0")]
+    #[new(value = "-1")]
     hdl: i32,
-    #[allow(unused)]
     #[new(value = "Filetype::EDF")]
     filetype: Filetype,
+    #[new(value = "Duration::from_secs(1)")]
+    datarecord_duration: Duration,
+    #[new(default)]
+    samples_per_record: Vec<i32>,
+    /// The signal the next per-signal write belongs to, EDFlib requires them in order.
+    #[new(default)]
+    next_signal: usize,
 }
 
+#[cfg(feature = "ffi")]
 #[derive(new)]
 pub struct Edf {
     path: PathBuf,
@@ -64,150 +154,527 @@ pub struct Edf {
     pub number_of_signals: i32,
 }
 
+#[cfg(feature = "ffi")]
 impl Edf {
     fn get_hdl(&self) -> i32 {
         let inner = self.inner.lock().unwrap();
         inner.hdl
     }
+
+    /// The type of the file being written, `EDFPLUS` or `BDFPLUS` once opened.
+    pub fn filetype(&self) -> Filetype {
+        let inner = self.inner.lock().unwrap();
+        inner.filetype
+    }
+
+    pub(crate) fn context(&self, operation: &'static str) -> ErrorContext {
+        ErrorContext::new(operation).path(&self.path)
+    }
+
+    fn check(&self, result: c_int, operation: &'static str) -> Result<()> {
+        EdfError::check(result, || self.context(operation)).map(|_| ())
+    }
+
+    fn check_signal(&self, result: c_int, operation: &'static str, edfsignal: i32) -> Result<()> {
+        EdfError::check(result, || self.context(operation).signal(edfsignal)).map(|_| ())
+    }
+
     pub fn open_file_writeonly(&self) -> Result<()> {
-        let path = PathBuf::from(self.path.to_str().unwrap());
-        let ext = path.extension().unwrap().to_str().unwrap();
-        let filetype = Filetype::from(ext);
+        self.open_file_writeonly_as(Filetype::from_path(&self.path))
+    }
+
+    /// Opens the file as EDF+ or BDF+ regardless of the extension of its path.
+    pub(crate) fn open_file_writeonly_as(&self, filetype: Filetype) -> Result<()> {
+        let path = path_to_cstring(&self.path, || self.context("open_file_writeonly"))?;
 
-        let path = str_to_char(path.to_str().unwrap());
         let mut inner = self.inner.lock().unwrap();
 
         let filetype = match filetype {
-            Filetype::EDF => EDFLIB_FILETYPE_EDFPLUS as c_int,
-            Filetype::BDF => EDFLIB_FILETYPE_BDFPLUS as c_int,
+            Filetype::EDF | Filetype::EDFPLUS => EDFLIB_FILETYPE_EDFPLUS as c_int,
+            Filetype::BDF | Filetype::BDFPLUS => EDFLIB_FILETYPE_BDFPLUS as c_int,
+        };
+        let hdl = unsafe {
+            ffi::open_file_writeonly(path.as_ptr(), filetype, self.number_of_signals)
         };
-        let hdl = unsafe { edfopen_file_writeonly(path, filetype, self.number_of_signals) };
-        inner.hdl = hdl;
 
         if hdl < 0 {
-            let msg = format!("Can not open file \"{}\"for writing", self.path.to_str().unwrap());
-            Err(anyhow!(msg))
+            Err(EdfError::from_code(hdl, self.context("open_file_writeonly")))
         } else {
+            inner.hdl = hdl;
+            inner.filetype = Filetype::from_raw(filetype).unwrap_or(Filetype::EDFPLUS);
+            inner.samples_per_record = vec![0; self.number_of_signals.max(0) as usize];
             Ok(())
         }
     }
 
-    pub fn finish(&self) -> Result<()> {
-        let result = unsafe { edfclose_file(self.get_hdl()) };
+    /// Finalizes the header and closes the file, consuming the writer.
+    ///
+    /// Dropping an `Edf` does the same, but can only log a failure.
+    pub fn finish(self) -> Result<()> {
+        self.close()
+    }
 
-        if result < 0 {
-            Err(anyhow!("Error finishing and closing the file"))
-        } else {
-            Ok(())
+    pub(crate) fn close(&self) -> Result<()> {
+        let hdl = {
+            let mut inner = self.inner.lock().unwrap();
+            std::mem::replace(&mut inner.hdl, -1)
+        };
+        if hdl < 0 {
+            return Ok(());
         }
+
+        let result = unsafe { ffi::close_file(hdl) };
+        self.check(result, "finish")
     }
 
+    /// Sets the number of samples of `edfsignal` per datarecord, which is the sample frequency
+    /// only with the default datarecord duration of one second.
     pub fn set_samplefrequency(&self, edfsignal: i32, samplefrequency: i32) -> Result<()> {
         let result = unsafe { edf_set_samplefrequency(self.get_hdl(), edfsignal, samplefrequency) };
+        self.check_signal(result, "set_samplefrequency", edfsignal)?;
 
-        if result < 0 {
-            Err(anyhow!("Error setting set_samplefrequency"))
-        } else {
-            Ok(())
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(samples) = inner.samples_per_record.get_mut(edfsignal as usize) {
+            *samples = samplefrequency;
         }
+        Ok(())
     }
 
+    /// Sets the datarecord duration, one second by default.
+    ///
+    /// Durations from 1 ms to 60 s in steps of 10 us go through `edf_set_datarecord_duration`,
+    /// shorter ones from 1 us to 9999 us through `edf_set_micro_datarecord_duration`.
+    pub fn set_datarecord_duration(&self, duration: Duration) -> Result<()> {
+        let context = || self.context("set_datarecord_duration");
+        let result = match datarecord_duration_units(duration) {
+            Some(DatarecordUnits::TenMicros(units)) => unsafe {
+                edf_set_datarecord_duration(self.get_hdl(), units)
+            },
+            Some(DatarecordUnits::Micros(units)) => unsafe {
+                edf_set_micro_datarecord_duration(self.get_hdl(), units)
+            }
+            None => {
+                return Err(
+                    EdfError::InvalidArgument(
+                        format!("datarecord duration {:?} is not supported", duration),
+                        context()
+                    )
+                );
+            }
+        };
+        self.check(result, "set_datarecord_duration")?;
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.datarecord_duration = duration;
+        Ok(())
+    }
+
+    pub fn datarecord_duration(&self) -> Duration {
+        let inner = self.inner.lock().unwrap();
+        inner.datarecord_duration
+    }
+
+    /// Samples per datarecord of every signal, as set with [`Edf::set_samplefrequency`].
+    pub fn samples_per_record(&self) -> Vec<i32> {
+        let inner = self.inner.lock().unwrap();
+        inner.samples_per_record.clone()
+    }
+
+    /// Effective sample frequency of `edfsignal` in Hz: samples per datarecord divided by the
+    /// datarecord duration.
+    pub fn sample_frequency(&self, edfsignal: i32) -> Option<f64> {
+        let inner = self.inner.lock().unwrap();
+        let samples = *inner.samples_per_record.get(usize::try_from(edfsignal).ok()?)?;
+        Some((samples as f64) / inner.datarecord_duration.as_secs_f64())
+    }
+
+    /// Sets the digital maximum, at most 32767 for EDF and 8388607 for BDF.
     pub fn set_digital_maximum(&self, edfsignal: i32, dig_max: i32) -> Result<()> {
+        check_digital(self.filetype(), dig_max, || {
+            self.context("set_digital_maximum").signal(edfsignal)
+        })?;
         let result = unsafe { edf_set_digital_maximum(self.get_hdl(), edfsignal, dig_max) };
-
-        if result < 0 {
-            Err(anyhow!("Error setting set_digital_maximum"))
-        } else {
-            Ok(())
-        }
+        self.check_signal(result, "set_digital_maximum", edfsignal)
     }
 
+    /// Sets the digital minimum, at least -32768 for EDF and -8388608 for BDF.
     pub fn set_digital_minimum(&self, edfsignal: i32, dig_min: i32) -> Result<()> {
+        check_digital(self.filetype(), dig_min, || {
+            self.context("set_digital_minimum").signal(edfsignal)
+        })?;
         let result = unsafe { edf_set_digital_minimum(self.get_hdl(), edfsignal, dig_min) };
+        self.check_signal(result, "set_digital_minimum", edfsignal)
+    }
 
-        if result < 0 {
-            Err(anyhow!("Error setting set_digital_minimum"))
-        } else {
-            Ok(())
-        }
+    /// Sets the physical maximum, the value of the ADC input at the digital maximum.
+    pub fn set_physical_maximum(&self, edfsignal: i32, phys_max: f64) -> Result<()> {
+        check_physical(phys_max, || self.context("set_physical_maximum").signal(edfsignal))?;
+        let result = unsafe { edf_set_physical_maximum(self.get_hdl(), edfsignal, phys_max) };
+        self.check_signal(result, "set_physical_maximum", edfsignal)
     }
 
-    pub fn set_physical_dimension(&self, edfsignal: i32, phys_dim: String) -> Result<()> {
-        let phys_dim = str_to_char(phys_dim.as_str());
-        let result = unsafe { edf_set_physical_dimension(self.get_hdl(), edfsignal, phys_dim) };
+    /// Sets the physical minimum, the value of the ADC input at the digital minimum.
+    pub fn set_physical_minimum(&self, edfsignal: i32, phys_min: f64) -> Result<()> {
+        check_physical(phys_min, || self.context("set_physical_minimum").signal(edfsignal))?;
+        let result = unsafe { edf_set_physical_minimum(self.get_hdl(), edfsignal, phys_min) };
+        self.check_signal(result, "set_physical_minimum", edfsignal)
+    }
 
-        if result < 0 {
-            Err(anyhow!("Error setting set_physical_dimension"))
-        } else {
-            Ok(())
-        }
+    pub fn set_physical_dimension(&self, edfsignal: i32, phys_dim: impl AsRef<str>) -> Result<()> {
+        let phys_dim = to_latin1(phys_dim.as_ref(), || {
+            self.context("set_physical_dimension").signal(edfsignal)
+        })?;
+        let result = unsafe {
+            edf_set_physical_dimension(self.get_hdl(), edfsignal, phys_dim.as_ptr())
+        };
+        self.check_signal(result, "set_physical_dimension", edfsignal)
     }
 
-    pub fn set_label(&self, edfsignal: i32, label: String) -> Result<()> {
-        let label = str_to_char(label.as_str());
-        let result = unsafe { edf_set_label(self.get_hdl(), edfsignal, label) };
+    pub fn set_label(&self, edfsignal: i32, label: impl AsRef<str>) -> Result<()> {
+        let label = to_latin1(label.as_ref(), || self.context("set_label").signal(edfsignal))?;
+        let result = unsafe { edf_set_label(self.get_hdl(), edfsignal, label.as_ptr()) };
+        self.check_signal(result, "set_label", edfsignal)
+    }
 
-        if result < 0 {
-            Err(anyhow!("Error setting set_label"))
-        } else {
-            Ok(())
-        }
+    /// Sets the prefilter, e.g. `HP:0.1Hz LP:75Hz`, at most 80 characters.
+    pub fn set_prefilter(&self, edfsignal: i32, prefilter: impl AsRef<str>) -> Result<()> {
+        let context = || self.context("set_prefilter").signal(edfsignal);
+        check_field(prefilter.as_ref(), "prefilter", MAX_SIGNAL_FIELD_LEN, context)?;
+        let prefilter = to_latin1(prefilter.as_ref(), context)?;
+        let result = unsafe { edf_set_prefilter(self.get_hdl(), edfsignal, prefilter.as_ptr()) };
+        self.check_signal(result, "set_prefilter", edfsignal)
     }
 
-    pub fn set_equipment(&self, equipment: String) -> Result<()> {
-        let equipment = str_to_char(equipment.as_str());
-        let result = unsafe { edf_set_equipment(self.get_hdl(), equipment) };
+    /// Sets the transducer, e.g. `AgAgCl cup electrodes`, at most 80 characters.
+    pub fn set_transducer(&self, edfsignal: i32, transducer: impl AsRef<str>) -> Result<()> {
+        let context = || self.context("set_transducer").signal(edfsignal);
+        check_field(transducer.as_ref(), "transducer", MAX_SIGNAL_FIELD_LEN, context)?;
+        let transducer = to_latin1(transducer.as_ref(), context)?;
+        let result = unsafe {
+            edf_set_transducer(self.get_hdl(), edfsignal, transducer.as_ptr())
+        };
+        self.check_signal(result, "set_transducer", edfsignal)
+    }
 
-        if result < 0 {
-            Err(anyhow!("Error setting set_equipment"))
-        } else {
-            Ok(())
+    /// Sets the start date and time, including the subsecond part.
+    ///
+    /// If never called, EDFlib uses the system time when the file was opened.
+    pub fn set_startdatetime(&self, start: &StartDateTime) -> Result<()> {
+        start.validate(|| self.context("set_startdatetime"))?;
+        let result = unsafe {
+            edf_set_startdatetime(
+                self.get_hdl(),
+                start.year,
+                start.month,
+                start.day,
+                start.hour,
+                start.minute,
+                start.second
+            )
+        };
+        self.check(result, "set_startdatetime")?;
+
+        let result = unsafe {
+            edf_set_subsecond_starttime(self.get_hdl(), start.subsecond_ticks())
+        };
+        self.check(result, "set_subsecond_starttime")
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn set_start_datetime(&self, start: chrono::NaiveDateTime) -> Result<()> {
+        self.set_startdatetime(&StartDateTime::from_naive(start))
+    }
+
+    /// Passes an EDF+ identification subfield to one of the `edf_set_*` string setters.
+    fn set_identification(
+        &self,
+        operation: &'static str,
+        value: &str,
+        setter: unsafe extern "C" fn(c_int, *const c_char) -> c_int
+    ) -> Result<()> {
+        let context = || self.context(operation);
+        let name = operation.trim_start_matches("set_");
+        check_ascii_field(value, name, info::MAX_IDENTIFICATION_LEN, context)?;
+        let value = to_latin1(value, context)?;
+        let result = unsafe { setter(self.get_hdl(), value.as_ptr()) };
+        self.check(result, operation)
+    }
+
+    pub fn set_patientname(&self, patientname: impl AsRef<str>) -> Result<()> {
+        self.set_identification("set_patientname", patientname.as_ref(), edf_set_patientname)
+    }
+
+    pub fn set_patientcode(&self, patientcode: impl AsRef<str>) -> Result<()> {
+        self.set_identification("set_patientcode", patientcode.as_ref(), edf_set_patientcode)
+    }
+
+    pub fn set_sex(&self, sex: Sex) -> Result<()> {
+        let result = unsafe { edf_set_sex(self.get_hdl(), sex.to_raw()) };
+        self.check(result, "set_sex")
+    }
+
+    pub fn set_birthdate(&self, birthdate: Birthdate) -> Result<()> {
+        birthdate.validate(|| self.context("set_birthdate"))?;
+        let result = unsafe {
+            edf_set_birthdate(self.get_hdl(), birthdate.year, birthdate.month, birthdate.day)
+        };
+        self.check(result, "set_birthdate")
+    }
+
+    pub fn set_patient_additional(&self, patient_additional: impl AsRef<str>) -> Result<()> {
+        self.set_identification(
+            "set_patient_additional",
+            patient_additional.as_ref(),
+            edf_set_patient_additional
+        )
+    }
+
+    pub fn set_admincode(&self, admincode: impl AsRef<str>) -> Result<()> {
+        self.set_identification("set_admincode", admincode.as_ref(), edf_set_admincode)
+    }
+
+    pub fn set_technician(&self, technician: impl AsRef<str>) -> Result<()> {
+        self.set_identification("set_technician", technician.as_ref(), edf_set_technician)
+    }
+
+    pub fn set_equipment(&self, equipment: impl AsRef<str>) -> Result<()> {
+        self.set_identification("set_equipment", equipment.as_ref(), edf_set_equipment)
+    }
+
+    pub fn set_recording_additional(&self, recording_additional: impl AsRef<str>) -> Result<()> {
+        self.set_identification(
+            "set_recording_additional",
+            recording_additional.as_ref(),
+            edf_set_recording_additional
+        )
+    }
+
+    /// Validates and writes all patient subfields at once.
+    pub fn set_patient_info(&self, patient: &PatientInfo) -> Result<()> {
+        patient.validate(|| self.context("set_patient_info"))?;
+        self.set_patientcode(&patient.code)?;
+        if let Some(sex) = patient.sex {
+            self.set_sex(sex)?;
+        }
+        if let Some(birthdate) = patient.birthdate {
+            self.set_birthdate(birthdate)?;
         }
+        self.set_patientname(&patient.name)?;
+        self.set_patient_additional(&patient.additional)
+    }
+
+    /// Validates and writes all recording subfields at once.
+    pub fn set_recording_info(&self, recording: &RecordingInfo) -> Result<()> {
+        recording.validate(|| self.context("set_recording_info"))?;
+        self.set_admincode(&recording.admincode)?;
+        self.set_technician(&recording.technician)?;
+        self.set_equipment(&recording.equipment)?;
+        self.set_recording_additional(&recording.additional)
     }
 
     pub fn set_annot_chan_idx_pos(&self, position: AnnotationPosition) -> Result<()> {
         let result = unsafe { edf_set_annot_chan_idx_pos(self.get_hdl(), position.to_raw()) };
-
-        if result < 0 {
-            Err(anyhow!("Error setting set_annot_chan_idx_pos"))
-        } else {
-            Ok(())
-        }
+        self.check(result, "set_annot_chan_idx_pos")
     }
 
     pub fn set_number_of_annotation_signals(&self, annot_signals: usize) -> Result<()> {
         let result = unsafe {
             edf_set_number_of_annotation_signals(self.get_hdl(), annot_signals as i32)
         };
+        self.check(result, "set_number_of_annotation_signals")
+    }
+
+    /// Writes one datarecord of physical samples for the next signal.
+    ///
+    /// EDFlib expects the signals in order, 0, 1, .., n - 1, 0, 1, .., and `samples` has to
+    /// hold exactly the samples per datarecord of that signal.
+    pub fn write_samples(&self, samples: impl AsRef<[f64]>) -> Result<()> {
+        let samples = samples.as_ref();
+        let edfsignal = self.check_next_signal("write_samples", samples.len())?;
+
+        // EDFlib only reads from the buffer
+        let buf = samples.as_ptr().cast_mut();
+        let result = unsafe { edfwrite_physical_samples(self.get_hdl(), buf) };
+        self.check_signal(result, "write_samples", edfsignal)?;
+        self.advance_signal();
+        Ok(())
+    }
+
+    /// Writes one datarecord of raw 16 bit samples for the next signal, EDF only.
+    ///
+    /// The values go to the file without physical to digital conversion, EDFlib clamps them to
+    /// the digital minimum and maximum of the signal.
+    pub fn write_digital_short_samples(&self, samples: &[i16]) -> Result<()> {
+        let edfsignal = self.check_next_signal("write_digital_short_samples", samples.len())?;
+        if !matches!(self.filetype(), Filetype::EDF | Filetype::EDFPLUS) {
+            return Err(
+                EdfError::FiletypeError(
+                    self.context("write_digital_short_samples").signal(edfsignal)
+                )
+            );
+        }
+
+        // EDFlib clamps the values in place, so it gets a copy
+        let mut buf = samples.to_vec();
+        let result = unsafe {
+            edfwrite_digital_short_samples(self.get_hdl(), buf.as_mut_ptr().cast::<c_short>())
+        };
+        self.check_signal(result, "write_digital_short_samples", edfsignal)?;
+        self.advance_signal();
+        Ok(())
+    }
 
-        if result < 0 {
-            Err(anyhow!("Error setting set_number_of_annotation_signals"))
-        } else {
-            Ok(())
+    /// Writes one datarecord of raw samples for the next signal.
+    ///
+    /// Every value has to fit the sample width of the file, 16 bit for EDF and 24 bit for BDF.
+    /// EDFlib clamps the values to the digital minimum and maximum of the signal.
+    pub fn write_digital_samples(&self, samples: &[i32]) -> Result<()> {
+        let edfsignal = self.check_next_signal("write_digital_samples", samples.len())?;
+        let filetype = self.filetype();
+        for &sample in samples {
+            check_digital(filetype, sample, || {
+                self.context("write_digital_samples").signal(edfsignal)
+            })?;
         }
+
+        // EDFlib only reads from the buffer
+        let buf = samples.as_ptr().cast_mut();
+        let result = unsafe { edfwrite_digital_samples(self.get_hdl(), buf) };
+        self.check_signal(result, "write_digital_samples", edfsignal)?;
+        self.advance_signal();
+        Ok(())
     }
 
-    pub fn write_samples(&self, samples: &mut Vec<f64>) -> Result<()> {
-        let buf: *mut f64 = samples.as_mut_ptr().cast::<f64>();
-        let result = unsafe { edfwrite_physical_samples(self.get_hdl(), buf) };
+    /// Checks `len` against the samples per datarecord of the signal EDFlib expects next and
+    /// returns that signal.
+    fn check_next_signal(&self, operation: &'static str, len: usize) -> Result<i32> {
+        let (edfsignal, expected) = {
+            let inner = self.inner.lock().unwrap();
+            let expected = inner.samples_per_record.get(inner.next_signal).copied();
+            (inner.next_signal as i32, expected)
+        };
+        let context = || self.context(operation).signal(edfsignal);
+
+        match expected {
+            None => Err(EdfError::NoSignals(context())),
+            Some(expected) if (expected.max(0) as usize) != len => {
+                Err(
+                    EdfError::InvalidArgument(
+                        format!("expected {} samples, got {}", expected, len),
+                        context()
+                    )
+                )
+            }
+            Some(_) => Ok(edfsignal),
+        }
+    }
 
-        if result < 0 {
-            Err(anyhow!("Error write_samples"))
-        } else {
-            Ok(())
+    fn advance_signal(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_signal = (inner.next_signal + 1) % inner.samples_per_record.len().max(1);
+    }
+
+    /// A zeroed [`RecordBuffer`] matching the configured samples per datarecord.
+    pub fn record_buffer<T: RecordSample>(&self) -> RecordBuffer<T> {
+        let samples_per_record: Vec<usize> = self
+            .samples_per_record()
+            .into_iter()
+            .map(|samples| samples.max(0) as usize)
+            .collect();
+        RecordBuffer::new(&samples_per_record)
+    }
+
+    /// Writes a whole datarecord with a single `edf_blockwrite_*` call.
+    ///
+    /// The length of every signal in `record` is checked against the configured samples per
+    /// datarecord before the buffer is handed to the C library.
+    pub fn write_record<T: RecordSample>(&self, record: &RecordBuffer<T>) -> Result<()> {
+        let context = || self.context(T::OPERATION);
+        if !T::supports(self.filetype()) {
+            return Err(EdfError::FiletypeError(context()));
+        }
+        let next_signal = self.inner.lock().unwrap().next_signal;
+        if next_signal != 0 {
+            return Err(
+                EdfError::InvalidArgument(
+                    format!("the current datarecord is only written up to signal {}", next_signal),
+                    context()
+                )
+            );
+        }
+
+        let samples_per_record = self.samples_per_record();
+        if record.number_of_signals() != samples_per_record.len() {
+            return Err(
+                EdfError::InvalidArgument(
+                    format!(
+                        "record has {} signals, the file {}",
+                        record.number_of_signals(),
+                        samples_per_record.len()
+                    ),
+                    context()
+                )
+            );
         }
+        for (edfsignal, &expected) in samples_per_record.iter().enumerate() {
+            let actual = record.samples_per_record(edfsignal).unwrap_or_default();
+            if actual != (expected.max(0) as usize) {
+                return Err(
+                    EdfError::InvalidArgument(
+                        format!("expected {} samples, got {}", expected, actual),
+                        context().signal(edfsignal as i32)
+                    )
+                );
+            }
+        }
+
+        let result = unsafe { T::blockwrite(self.get_hdl(), record.as_slice()) };
+        self.check(result, T::OPERATION)
     }
 
-    pub fn write_annotation(&self, onset: i64, duration: i64, description: String) -> Result<()> {
-        let description = str_to_char(description.as_str());
+    /// Writes an annotation, `text` is stored as UTF-8. EDFlib keeps the first 40 bytes
+    /// (`EDFLIB_WRITE_MAX_ANNOTATION_LEN`), longer texts are cut at the last character boundary
+    /// before that.
+    ///
+    /// `onset` is relative to the start of the recording, both times are written with microsecond
+    /// resolution. Use `None` for events without a duration.
+    pub fn write_annotation(
+        &self,
+        onset: Duration,
+        duration: Option<Duration>,
+        text: impl AsRef<str>
+    ) -> Result<()> {
+        let context = || self.context("write_annotation");
+        let invalid = |message: String| Err(EdfError::InvalidArgument(message, context()));
+
+        let text = to_utf8(truncate_annotation(text.as_ref()), context)?;
+
+        let Ok(onset) = i64::try_from(onset.as_micros()) else {
+            return invalid(format!("onset {:?} is out of range", onset));
+        };
+        let duration = match duration {
+            None => -1,
+            Some(duration) =>
+                match i64::try_from(duration.as_micros()) {
+                    Ok(micros) => micros,
+                    Err(_) => {
+                        return invalid(format!("duration {:?} is out of range", duration));
+                    }
+                }
+        };
+
         let result = unsafe {
-            edfwrite_annotation_latin1_hr(self.get_hdl(), onset, duration, description)
+            edfwrite_annotation_utf8_hr(self.get_hdl(), onset, duration, text.as_ptr())
         };
+        self.check(result, "write_annotation")
+    }
+}
 
-        if result < 0 {
-            Err(anyhow!("Error write_annotation"))
-        } else {
-            Ok(())
+#[cfg(feature = "ffi")]
+impl Drop for Edf {
+    fn drop(&mut self) {
+        if let Err(error) = self.close() {
+            log::error!("{}", error);
         }
     }
 }
@@ -0,0 +1,533 @@
+use std::{ io::{ Read, Seek, SeekFrom }, str::FromStr, time::Duration };
+
+use super::{ io_error, tal::parse_ticks, HEADER_BLOCK };
+use crate::{
+    error::*,
+    header::*,
+    info::*,
+    utils::{ ticks_to_duration, MAX_SIGNALS },
+    Filetype,
+};
+
+const MONTHS: [&str; 12] = [
+    "JAN",
+    "FEB",
+    "MAR",
+    "APR",
+    "MAY",
+    "JUN",
+    "JUL",
+    "AUG",
+    "SEP",
+    "OCT",
+    "NOV",
+    "DEC",
+];
+
+/// Where the samples of a data signal are stored in a datarecord and how they convert to
+/// physical values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SignalLayout {
+    /// Byte offset of the first sample in the datarecord.
+    pub offset: usize,
+    pub samples_in_datarecord: usize,
+    pub digital_minimum: i32,
+    pub digital_maximum: i32,
+    /// Physical value of one digital step.
+    pub gain: f64,
+    /// Added to the digital value before scaling, computed the way EDFlib does.
+    pub shift: f64,
+}
+
+impl SignalLayout {
+    /// Out of range digital values are clamped, like EDFlib does.
+    pub fn physical(&self, digital: i32) -> f64 {
+        let digital = digital.clamp(self.digital_minimum, self.digital_maximum);
+        self.gain * (self.shift + (digital as f64))
+    }
+
+    /// The inverse of [`SignalLayout::physical`], truncating towards zero.
+    pub fn digital(&self, physical: f64) -> i32 {
+        let digital = physical / self.gain - self.shift;
+        digital.clamp(self.digital_minimum as f64, self.digital_maximum as f64) as i32
+    }
+}
+
+/// Byte layout of the datarecords of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Layout {
+    pub header_size: u64,
+    pub record_size: usize,
+    pub datarecords: i64,
+    /// Bytes per sample, 2 for EDF and 3 for BDF.
+    pub sample_size: usize,
+    /// The data signals, in the order of [`Header::signals`].
+    pub signals: Vec<SignalLayout>,
+    /// Byte offset and size of every annotation signal in the datarecord.
+    pub annotation_signals: Vec<(usize, usize)>,
+}
+
+impl Layout {
+    /// Byte offset of `record` in the file, [`read_header`] checks that the end of the last
+    /// datarecord fits in a u64.
+    pub fn record_offset(&self, record: i64) -> u64 {
+        self.header_size + (record.max(0) as u64) * (self.record_size as u64)
+    }
+}
+
+/// Decodes little endian 16 bit (EDF) or 24 bit (BDF) samples.
+pub(crate) fn decode_digital(raw: &[u8], sample_size: usize) -> impl Iterator<Item = i32> + '_ {
+    raw.chunks_exact(sample_size).map(move |sample| {
+        if sample_size == 2 {
+            i16::from_le_bytes([sample[0], sample[1]]) as i32
+        } else {
+            // shift the sign bit of the 24 bit value into place and back
+            i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) >> 8
+        }
+    })
+}
+
+/// Encodes little endian 16 bit (EDF) or 24 bit (BDF) samples, the inverse of
+/// [`decode_digital`].
+pub(crate) fn encode_digital(digital: i32, sample: &mut [u8]) {
+    let bytes = digital.to_le_bytes();
+    sample.copy_from_slice(&bytes[..sample.len()]);
+}
+
+/// The general header is stored field by field, the signal headers column by column.
+struct Fields<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        let (field, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        field
+    }
+
+    fn column(&mut self, count: usize, len: usize) -> Vec<&'a [u8]> {
+        (0..count).map(|_| self.take(len)).collect()
+    }
+}
+
+fn text(field: &[u8]) -> String {
+    String::from_utf8_lossy(field).trim_end().to_string()
+}
+
+fn number<T: FromStr>(field: &[u8]) -> Option<T> {
+    std::str::from_utf8(field).ok()?.trim().parse().ok()
+}
+
+/// `dd.mm.yy` and `hh.mm.ss`, two digit years are 1985 - 2084.
+fn start_date_time(date: &[u8], time: &[u8]) -> Option<StartDateTime> {
+    let part = |field: &[u8], index: usize| -> Option<i32> {
+        let digits = &field[index * 3..index * 3 + 2];
+        if index < 2 && field[index * 3 + 2] != b'.' {
+            return None;
+        }
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        number(digits)
+    };
+    let year = part(date, 2)?;
+
+    let start = StartDateTime {
+        year: if year > 84 { 1900 + year } else { 2000 + year },
+        month: part(date, 1)?,
+        day: part(date, 0)?,
+        hour: part(time, 0)?,
+        minute: part(time, 1)?,
+        second: part(time, 2)?,
+        subsecond: Duration::ZERO,
+    };
+    start.validate(|| ErrorContext::new("open")).ok()?;
+    Some(start)
+}
+
+/// An EDF+ date, `dd-MMM-yyyy`, as `(day, month, year)`.
+fn plus_date(value: &str) -> Option<(i32, i32, i32)> {
+    let mut parts = value.split('-');
+    let day = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|name| name.eq_ignore_ascii_case(month))?;
+    let year = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some((day, (month as i32) + 1, year))
+}
+
+/// `edflib_latin1_to_ascii`: the ASCII character EDFlib writes for each of the Latin-1
+/// characters 127 to 255.
+const LATIN1_TO_ASCII: &[u8; 129] =
+    b".E.,F\".++^.S<E.Z..`'\"\".--~.s>e.zY.i....|....<...-....'u.....>...?\
+      AAAAAAECEEEEIIIIDNOOOOOxOUUUUYtsaaaaaaeceeeeiiiidnooooo:0uuuuyty";
+
+/// Appends `value` as a header field of `len` bytes, padded with spaces. The header only holds
+/// printable ASCII, like EDFlib control characters are written as `.` and Latin-1 characters
+/// as the closest ASCII character.
+pub(crate) fn push_field(header: &mut Vec<u8>, value: &str, len: usize) {
+    let start = header.len();
+    header.extend(
+        value
+            .chars()
+            .map(|c| {
+                match c as u32 {
+                    32..=126 => c as u8,
+                    code @ 127..=255 => LATIN1_TO_ASCII[(code as usize) - 127],
+                    _ => b'.',
+                }
+            })
+            .take(len)
+    );
+    header.resize(start + len, b' ');
+}
+
+/// Formats a number for an 8 character header field like `edflib_snprint_number_nonlocalized`:
+/// the integer part and up to 9 decimals, truncated rather than rounded.
+pub(crate) fn format_number(value: f64) -> String {
+    let integer = value as i32;
+    let fraction = ((value - (integer as f64)) * 1e9) as i32;
+
+    let mut text = String::new();
+    if value < 0.0 {
+        text.push('-');
+    }
+    text.push_str(&integer.unsigned_abs().to_string());
+    if fraction != 0 {
+        text.push('.');
+        text.push_str(format!("{:09}", fraction.unsigned_abs()).trim_end_matches('0'));
+    }
+    text
+}
+
+fn format_plus_date(day: i32, month: i32, year: i32) -> String {
+    let month = MONTHS.get((month as usize).wrapping_sub(1)).unwrap_or(&"JAN");
+    format!("{:02}-{}-{:04}", day, month, year)
+}
+
+/// Appends an EDF+ identification subfield the way EDFlib does, the inverse of [`subfield`].
+/// Spaces around the value are dropped and spaces inside become `_`, the value is cut to the
+/// `rest` of the field. Returns `false`, and appends nothing, for an empty value or no rest.
+fn push_subfield(identification: &mut String, value: &str, rest: &mut usize) -> bool {
+    let value = value.trim_matches(' ');
+    if value.is_empty() || *rest == 0 {
+        return false;
+    }
+    let len = value.len().min(*rest);
+    *rest = rest.saturating_sub(value.len());
+    identification.push_str(&value[..len].replace(' ', "_"));
+    true
+}
+
+/// Appends the free text at the end of an EDF+ identification, cut to the `rest` of the field.
+fn push_additional(identification: &mut String, value: &str, rest: usize) {
+    let value = value.trim_matches(' ');
+    identification.push_str(&value[..value.len().min(rest)]);
+}
+
+/// The EDF+ patient identification, `code sex birthdate name additional`. Code, name and
+/// additional share 72 characters, 62 with a birthdate.
+pub(crate) fn format_patient(patient: &PatientInfo) -> String {
+    let mut rest = if patient.birthdate.is_some() { 62 } else { 72 };
+    let mut identification = String::new();
+
+    if push_subfield(&mut identification, &patient.code, &mut rest) {
+        identification.push(' ');
+    } else {
+        identification.push_str("X ");
+    }
+    identification.push_str(
+        match patient.sex {
+            Some(Sex::Female) => "F ",
+            Some(Sex::Male) => "M ",
+            None => "X ",
+        }
+    );
+    match &patient.birthdate {
+        Some(birthdate) => {
+            identification.push_str(
+                &format_plus_date(birthdate.day, birthdate.month, birthdate.year)
+            );
+            identification.push(' ');
+        }
+        None => identification.push_str("X "),
+    }
+    if !push_subfield(&mut identification, &patient.name, &mut rest) {
+        identification.push('X');
+    }
+    if rest > 0 {
+        identification.push(' ');
+        rest -= 1;
+    }
+    push_additional(&mut identification, &patient.additional, rest);
+    identification
+}
+
+/// The EDF+ recording identification,
+/// `Startdate dd-MMM-yyyy admincode technician equipment additional`. Everything after the
+/// start date shares 52 characters.
+pub(crate) fn format_recording(recording: &RecordingInfo, start: &StartDateTime) -> String {
+    let mut rest = 52;
+    let mut identification =
+        format!("Startdate {} ", format_plus_date(start.day, start.month, start.year));
+
+    for value in [&recording.admincode, &recording.technician, &recording.equipment] {
+        if push_subfield(&mut identification, value, &mut rest) {
+            identification.push(' ');
+        } else {
+            identification.push_str("X ");
+        }
+    }
+    push_additional(&mut identification, &recording.additional, rest);
+    identification
+}
+
+/// An EDF+ identification subfield: `X` is unknown, spaces are written as `_`.
+fn subfield(value: &str) -> String {
+    if value == "X" { String::new() } else { value.replace('_', " ") }
+}
+
+/// Splits the EDF+ patient identification, `code sex birthdate name additional`.
+fn parse_patient(header: &mut Header, patient: &str) -> Option<()> {
+    let mut parts = patient.splitn(5, ' ');
+    header.patientcode = subfield(parts.next()?);
+    header.sex = (
+        match parts.next()? {
+            "F" => "Female",
+            "M" => "Male",
+            _ => "",
+        }
+    ).to_string();
+    let birthdate = parts.next()?;
+    if let Some((day, month, year)) = plus_date(birthdate) {
+        header.birthdate = format!(
+            "{:02} {} {}",
+            day,
+            MONTHS[(month as usize) - 1].to_lowercase(),
+            year
+        );
+        header.birthdate_day = day;
+        header.birthdate_month = month;
+        header.birthdate_year = year;
+    }
+    header.patient_name = subfield(parts.next()?);
+    header.patient_additional = parts.next().unwrap_or_default().to_string();
+    Some(())
+}
+
+/// Splits the EDF+ recording identification,
+/// `Startdate dd-MMM-yyyy admincode technician equipment additional`. The startdate has a four
+/// digit year and takes precedence over the two digit year of the header.
+fn parse_recording(header: &mut Header, recording: &str) -> Option<()> {
+    let mut parts = recording.splitn(6, ' ');
+    if parts.next()? != "Startdate" {
+        return None;
+    }
+    if let Some((_, _, year)) = plus_date(parts.next()?) {
+        if (1985..=2084).contains(&year) {
+            header.start.year = year;
+        }
+    }
+    header.admincode = subfield(parts.next()?);
+    header.technician = subfield(parts.next()?);
+    header.equipment = subfield(parts.next()?);
+    header.recording_additional = parts.next().unwrap_or_default().to_string();
+    Some(())
+}
+
+/// Reads and checks the header. The start subsecond and the annotation count are filled in by
+/// the reader, they are stored in the datarecords.
+pub(crate) fn read_header<R: Read + Seek>(
+    source: &mut R,
+    context: impl Fn() -> ErrorContext
+) -> Result<(Header, Layout)> {
+    let format_error = || EdfError::FileContainsFormatErrors(context());
+
+    // EDFlib reports a header that ends early as a read error
+    let read_error = |_| EdfError::FileReadError(context());
+
+    let mut bytes = vec![0; HEADER_BLOCK];
+    source.seek(SeekFrom::Start(0)).map_err(|error| io_error(error, context()))?;
+    source.read_exact(&mut bytes).map_err(read_error)?;
+
+    let number_of_signals = number::<usize>(&bytes[252..256])
+        .filter(|signals| (1..=MAX_SIGNALS).contains(signals))
+        .ok_or_else(format_error)?;
+    bytes.resize(HEADER_BLOCK * (number_of_signals + 1), 0);
+    source.read_exact(&mut bytes[HEADER_BLOCK..]).map_err(read_error)?;
+
+    // everything but the first byte of the BDF version is printable ASCII
+    if !bytes[1..].iter().all(|c| (b' '..=b'~').contains(c)) {
+        return Err(format_error());
+    }
+
+    let mut fields = Fields { bytes: &bytes };
+    let bdf = match fields.take(8) {
+        b"0       " => false,
+        [0xff, b'B', b'I', b'O', b'S', b'E', b'M', b'I'] => true,
+        _ => {
+            return Err(format_error());
+        }
+    };
+    let patient = text(fields.take(80));
+    let recording = text(fields.take(80));
+    let start = start_date_time(fields.take(8), fields.take(8)).ok_or_else(format_error)?;
+    let header_size = number::<u64>(fields.take(8))
+        .filter(|&size| size == (bytes.len() as u64))
+        .ok_or_else(format_error)?;
+    let reserved = fields.take(44);
+    // EDFlib refuses files without datarecords, and -1 for a recording still in progress
+    let datarecords = number::<i64>(fields.take(8))
+        .filter(|&records| records >= 1)
+        .ok_or_else(format_error)?;
+    let datarecord_duration = std::str
+        ::from_utf8(fields.take(8))
+        .ok()
+        .and_then(|duration| parse_ticks(duration.trim(), false))
+        .filter(|&ticks| ticks >= 0)
+        .ok_or_else(format_error)?;
+    fields.take(4);
+
+    let (continuous, discontinuous): (&[u8], &[u8]) = if bdf {
+        (b"BDF+C", b"BDF+D")
+    } else {
+        (b"EDF+C", b"EDF+D")
+    };
+    // like EDFlib, which can not map the samples of a discontinuous file to a time
+    if reserved.starts_with(discontinuous) {
+        return Err(EdfError::FileIsDiscontinuous(context()));
+    }
+    let plus = reserved.starts_with(continuous);
+    let filetype = match (bdf, plus) {
+        (false, false) => Filetype::EDF,
+        (false, true) => Filetype::EDFPLUS,
+        (true, false) => Filetype::BDF,
+        (true, true) => Filetype::BDFPLUS,
+    };
+    let annotation_label: &[u8] = if bdf { b"BDF Annotations " } else { b"EDF Annotations " };
+    let sample_size = if bdf { 3 } else { 2 };
+    let (limit_min, limit_max) = filetype.digital_limits();
+
+    let labels = fields.column(number_of_signals, 16);
+    let transducers = fields.column(number_of_signals, 80);
+    let dimensions = fields.column(number_of_signals, 8);
+    let physical_minimums = fields.column(number_of_signals, 8);
+    let physical_maximums = fields.column(number_of_signals, 8);
+    let digital_minimums = fields.column(number_of_signals, 8);
+    let digital_maximums = fields.column(number_of_signals, 8);
+    let prefilters = fields.column(number_of_signals, 80);
+    let samples = fields.column(number_of_signals, 8);
+
+    let mut layout = Layout {
+        header_size,
+        record_size: 0,
+        datarecords,
+        sample_size,
+        signals: Vec::new(),
+        annotation_signals: Vec::new(),
+    };
+    let mut signals = Vec::new();
+    for signal in 0..number_of_signals {
+        let samples_in_datarecord = number::<usize>(samples[signal])
+            .filter(|&samples| samples >= 1)
+            .ok_or_else(format_error)?;
+        let size = samples_in_datarecord.checked_mul(sample_size).ok_or_else(format_error)?;
+        let offset = layout.record_size;
+        layout.record_size = offset.checked_add(size).ok_or_else(format_error)?;
+
+        if plus && labels[signal] == annotation_label {
+            layout.annotation_signals.push((offset, size));
+            continue;
+        }
+
+        let physical_minimum = number::<f64>(physical_minimums[signal]);
+        let physical_maximum = number::<f64>(physical_maximums[signal]);
+        let digital_minimum = number::<i32>(digital_minimums[signal]);
+        let digital_maximum = number::<i32>(digital_maximums[signal]);
+        let (
+            Some(physical_minimum),
+            Some(physical_maximum),
+            Some(digital_minimum),
+            Some(digital_maximum),
+        ) = (physical_minimum, physical_maximum, digital_minimum, digital_maximum) else {
+            return Err(format_error());
+        };
+        let valid =
+            physical_minimum.is_finite() &&
+            physical_maximum.is_finite() &&
+            physical_minimum != physical_maximum &&
+            digital_minimum < digital_maximum &&
+            digital_minimum >= limit_min &&
+            digital_maximum <= limit_max;
+        if !valid {
+            return Err(format_error());
+        }
+
+        let gain =
+            (physical_maximum - physical_minimum) /
+            ((digital_maximum as f64) - (digital_minimum as f64));
+        layout.signals.push(SignalLayout {
+            offset,
+            samples_in_datarecord,
+            digital_minimum,
+            digital_maximum,
+            gain,
+            shift: physical_maximum / gain - (digital_maximum as f64),
+        });
+        signals.push(SignalInfo {
+            label: text(labels[signal]),
+            physical_dimension: text(dimensions[signal]),
+            prefilter: text(prefilters[signal]),
+            transducer: text(transducers[signal]),
+            physical_maximum,
+            physical_minimum,
+            digital_maximum,
+            digital_minimum,
+            samples_in_file: (samples_in_datarecord as i64) * datarecords,
+            samples_in_datarecord: samples_in_datarecord as i32,
+        });
+    }
+    if plus && layout.annotation_signals.is_empty() {
+        return Err(format_error());
+    }
+    // Layout::record_offset relies on the end of the last datarecord fitting in a u64
+    (layout.record_size as u64)
+        .checked_mul(datarecords as u64)
+        .and_then(|size| size.checked_add(header_size))
+        .ok_or_else(format_error)?;
+
+    let mut header = Header {
+        filetype,
+        file_duration: ticks_to_duration(datarecord_duration.saturating_mul(datarecords)),
+        start,
+        patient: String::new(),
+        recording: String::new(),
+        patientcode: String::new(),
+        sex: String::new(),
+        birthdate: String::new(),
+        birthdate_day: 0,
+        birthdate_month: 0,
+        birthdate_year: 0,
+        patient_name: String::new(),
+        patient_additional: String::new(),
+        admincode: String::new(),
+        technician: String::new(),
+        equipment: String::new(),
+        recording_additional: String::new(),
+        datarecord_duration: ticks_to_duration(datarecord_duration),
+        datarecords_in_file: datarecords,
+        annotations_in_file: 0,
+        signals,
+    };
+    if plus {
+        parse_patient(&mut header, &patient).ok_or_else(format_error)?;
+        parse_recording(&mut header, &recording).ok_or_else(format_error)?;
+    } else {
+        header.patient = patient;
+        header.recording = recording;
+    }
+    Ok((header, layout))
+}
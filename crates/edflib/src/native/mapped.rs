@@ -0,0 +1,232 @@
+use std::{ fs::File, io::BufReader, path::{ Path, PathBuf } };
+
+use memmap2::Mmap;
+
+use super::{ header::*, io_error, NativeReader };
+use crate::{ annotation::*, error::*, header::* };
+
+/// A file memory-mapped for reading, enabled by the `mmap` feature.
+///
+/// The header and the annotations are parsed when the file is opened, samples are decoded from
+/// the mapping only when asked for, straight into the caller's buffer. Nothing is copied or
+/// locked, so any number of threads can read any part of the file at the same time.
+pub struct MappedEdf {
+    path: PathBuf,
+    map: Mmap,
+    header: Header,
+    layout: Layout,
+    annotations: Vec<RawAnnotation>,
+}
+
+impl MappedEdf {
+    /// Maps the file and parses its header and annotations.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped, not even by another
+    /// process, see [`memmap2::Mmap::map`].
+    pub unsafe fn open<P: AsRef<Path>>(path: P, read_annotations: ReadAnnotations) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let context = || ErrorContext::new("open").path(&path);
+        let file = File::open(&path).map_err(|error| io_error(error, context()))?;
+
+        let source = file.try_clone().map_err(|error| io_error(error, context()))?;
+        let (native, header) = NativeReader::open(
+            Box::new(BufReader::new(source)),
+            read_annotations,
+            context
+        )?;
+        let (layout, annotations) = native.into_parts();
+
+        let map = unsafe { Mmap::map(&file) }.map_err(|error| io_error(error, context()))?;
+        if (map.len() as u64) != layout.record_offset(layout.datarecords) {
+            return Err(EdfError::FileContainsFormatErrors(context()));
+        }
+
+        Ok(MappedEdf { path, map, header, layout, annotations })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn signals(&self) -> &[SignalInfo] {
+        &self.header.signals
+    }
+
+    pub fn number_of_signals(&self) -> usize {
+        self.header.signals.len()
+    }
+
+    /// The annotations read when the file was opened, see [`ReadAnnotations`].
+    pub fn annotations(&self) -> impl ExactSizeIterator<Item = Result<Annotation>> + '_ {
+        self.annotations
+            .iter()
+            .map(|annotation| {
+                annotation.to_annotation(|| ErrorContext::new("get_annotation").path(&self.path))
+            })
+    }
+
+    pub fn datarecords(&self) -> usize {
+        self.layout.datarecords.max(0) as usize
+    }
+
+    /// The datarecord at `index`, `None` past the end of the file.
+    pub fn datarecord(&self, index: usize) -> Option<Datarecord<'_>> {
+        (index < self.datarecords()).then(|| self.record(index))
+    }
+
+    /// All datarecords in the order of the file.
+    pub fn iter_datarecords(&self) -> impl ExactSizeIterator<Item = Datarecord<'_>> + '_ {
+        (0..self.datarecords()).map(move |index| self.record(index))
+    }
+
+    fn record(&self, index: usize) -> Datarecord<'_> {
+        let start = self.layout.record_offset(index as i64) as usize;
+        Datarecord {
+            bytes: &self.map[start..start + self.layout.record_size],
+            layout: &self.layout,
+            index,
+            path: &self.path,
+        }
+    }
+
+    fn check_signal(&self, signal: usize, operation: &'static str) -> Result<()> {
+        check_signal(&self.layout, signal, || {
+            ErrorContext::new(operation).path(&self.path).signal(signal as i32)
+        })
+    }
+
+    /// Decodes physical samples of `signal` starting at sample `start` into `buf`, across
+    /// datarecords, returns how many were decoded. Fewer than `buf.len()` are only returned at
+    /// the end of the signal.
+    pub fn read_physical(&self, signal: usize, start: u64, buf: &mut [f64]) -> Result<usize> {
+        self.check_signal(signal, "read_physical_samples")?;
+        let signal_layout = self.layout.signals[signal];
+        Ok(
+            self.read_samples(signal, start, buf, |target, digital| {
+                *target = signal_layout.physical(digital);
+            })
+        )
+    }
+
+    /// Decodes digital samples of `signal` starting at sample `start` into `buf`, across
+    /// datarecords, returns how many were decoded.
+    pub fn read_digital(&self, signal: usize, start: u64, buf: &mut [i32]) -> Result<usize> {
+        self.check_signal(signal, "read_digital_samples")?;
+        Ok(
+            self.read_samples(signal, start, buf, |target, digital| {
+                *target = digital;
+            })
+        )
+    }
+
+    fn read_samples<T>(
+        &self,
+        signal: usize,
+        start: u64,
+        buf: &mut [T],
+        mut decode: impl FnMut(&mut T, i32)
+    ) -> usize {
+        let per_record = self.layout.signals[signal].samples_in_datarecord as u64;
+        let samples_in_file = per_record * (self.datarecords() as u64);
+        let count = (buf.len() as u64).min(samples_in_file.saturating_sub(start)) as usize;
+
+        let mut done = 0;
+        while done < count {
+            let position = start + (done as u64);
+            let index = (position % per_record) as usize;
+            let n = ((per_record as usize) - index).min(count - done);
+
+            let record = self.record((position / per_record) as usize);
+            let raw = &record.raw(signal)[index * self.layout.sample_size..];
+            let samples = decode_digital(raw, self.layout.sample_size);
+            for (target, digital) in buf[done..done + n].iter_mut().zip(samples) {
+                decode(target, digital);
+            }
+            done += n;
+        }
+        count
+    }
+}
+
+/// A borrowed view of one datarecord of a [`MappedEdf`].
+#[derive(Clone, Copy)]
+pub struct Datarecord<'a> {
+    bytes: &'a [u8],
+    layout: &'a Layout,
+    index: usize,
+    path: &'a Path,
+}
+
+impl<'a> Datarecord<'a> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The raw bytes of the datarecord, annotation signals included.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    fn raw(&self, signal: usize) -> &'a [u8] {
+        let signal_layout = &self.layout.signals[signal];
+        let size = signal_layout.samples_in_datarecord * self.layout.sample_size;
+        &self.bytes[signal_layout.offset..signal_layout.offset + size]
+    }
+
+    /// The raw little endian samples of `signal`, 2 bytes each for EDF and 3 bytes for BDF.
+    pub fn signal_bytes(&self, signal: usize) -> Result<&'a [u8]> {
+        check_signal(self.layout, signal, || self.context("signal_bytes", signal))?;
+        Ok(self.raw(signal))
+    }
+
+    /// Decodes the physical samples of `signal` in this datarecord into `buf`, returns how many
+    /// were decoded.
+    pub fn read_physical(&self, signal: usize, buf: &mut [f64]) -> Result<usize> {
+        check_signal(self.layout, signal, || self.context("read_physical_samples", signal))?;
+        let signal_layout = self.layout.signals[signal];
+        let samples = decode_digital(self.raw(signal), self.layout.sample_size);
+        let mut count = 0;
+        for (target, digital) in buf.iter_mut().zip(samples) {
+            *target = signal_layout.physical(digital);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Decodes the digital samples of `signal` in this datarecord into `buf`, returns how many
+    /// were decoded.
+    pub fn read_digital(&self, signal: usize, buf: &mut [i32]) -> Result<usize> {
+        check_signal(self.layout, signal, || self.context("read_digital_samples", signal))?;
+        let samples = decode_digital(self.raw(signal), self.layout.sample_size);
+        let mut count = 0;
+        for (target, digital) in buf.iter_mut().zip(samples) {
+            *target = digital;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn context(&self, operation: &'static str, signal: usize) -> ErrorContext {
+        ErrorContext::new(operation)
+            .path(self.path)
+            .signal(signal as i32)
+    }
+}
+
+fn check_signal(layout: &Layout, signal: usize, context: impl Fn() -> ErrorContext) -> Result<()> {
+    if signal >= layout.signals.len() {
+        return Err(
+            EdfError::InvalidArgument(
+                format!("file has {} signals", layout.signals.len()),
+                context()
+            )
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! Native implementation of the EDF(+) and BDF(+) file format, enabled by the `pure-rust`
+//! feature. It follows the layout and the conventions of EDFlib, so both backends return the
+//! same header, samples and annotations for the same file, and write the same files.
+
+mod header;
+#[cfg(feature = "mmap")]
+mod mapped;
+mod reader;
+mod tal;
+mod writer;
+
+#[cfg(feature = "mmap")]
+pub use mapped::{ Datarecord, MappedEdf };
+pub(crate) use reader::{ NativeReader, Source };
+pub use writer::EdfWriter;
+pub(crate) use writer::WriterSpec;
+
+/// Size of the general header and of the header of every signal.
+pub(crate) const HEADER_BLOCK: usize = 256;
+/// `EDFLIB_TIME_DIMENSION`, time in the header and in annotations is kept in units of 100 ns.
+pub(crate) const TIME_DIMENSION: i64 = 10_000_000;
+/// `EDFLIB_MAX_ANNOTATION_LEN`
+pub(crate) const MAX_ANNOTATION_LEN: usize = 512;
+
+/// Maps I/O errors to the codes EDFlib reports for them, a file that ends early contains
+/// format errors.
+pub(crate) fn io_error(error: std::io::Error, context: crate::ErrorContext) -> crate::EdfError {
+    use std::io::ErrorKind;
+
+    match error.kind() {
+        ErrorKind::NotFound => crate::EdfError::NoSuchFileOrDirectory(context),
+        ErrorKind::UnexpectedEof => crate::EdfError::FileContainsFormatErrors(context),
+        _ => crate::EdfError::FileReadError(context),
+    }
+}
@@ -0,0 +1,199 @@
+use std::{ io::{ Read, Seek, SeekFrom }, sync::Mutex };
+
+use super::{ header::*, io_error, tal::parse_tals, MAX_ANNOTATION_LEN, TIME_DIMENSION };
+use crate::{ annotation::*, error::*, header::Header, utils::ticks_to_duration, Filetype };
+
+/// Anything the native backend can read a file from.
+pub(crate) trait Source: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> Source for T {}
+
+pub(crate) struct NativeReader<'s> {
+    layout: Layout,
+    annotations: Vec<RawAnnotation>,
+    state: Mutex<State<'s>>,
+}
+
+struct State<'s> {
+    source: Box<dyn Source + 's>,
+    /// Read position of every data signal in samples, EDFlib keeps one per signal.
+    positions: Vec<i64>,
+    /// Raw bytes of the samples being decoded.
+    raw: Vec<u8>,
+}
+
+impl<'s> NativeReader<'s> {
+    pub(crate) fn open(
+        mut source: Box<dyn Source + 's>,
+        read_annotations: ReadAnnotations,
+        context: impl Fn() -> ErrorContext
+    ) -> Result<(Self, Header)> {
+        let (mut header, layout) = read_header(&mut source, &context)?;
+
+        let size = source.seek(SeekFrom::End(0)).map_err(|error| io_error(error, context()))?;
+        // like EDFlib, the file has to end with the last datarecord
+        if size != layout.record_offset(layout.datarecords) {
+            return Err(EdfError::FileContainsFormatErrors(context()));
+        }
+
+        let mut reader = NativeReader {
+            annotations: Vec::new(),
+            state: Mutex::new(State {
+                source,
+                positions: vec![0; layout.signals.len()],
+                raw: Vec::new(),
+            }),
+            layout,
+        };
+        if matches!(header.filetype, Filetype::EDFPLUS | Filetype::BDFPLUS) {
+            let subsecond = reader.read_subsecond(&context)?;
+            header.start.subsecond = ticks_to_duration(subsecond);
+
+            reader.annotations = reader.read_annotations(subsecond, read_annotations, &context)?;
+            header.annotations_in_file = reader.annotations.len() as i64;
+        }
+        Ok((reader, header))
+    }
+
+    pub(crate) fn annotations(&self) -> &[RawAnnotation] {
+        &self.annotations
+    }
+
+    /// The layout and the annotations, for readers that access the data some other way.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn into_parts(self) -> (Layout, Vec<RawAnnotation>) {
+        (self.layout, self.annotations)
+    }
+
+    fn read_at(
+        &self,
+        offset: u64,
+        buf: &mut [u8],
+        context: impl Fn() -> ErrorContext
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.source.seek(SeekFrom::Start(offset)).map_err(|error| io_error(error, context()))?;
+        state.source.read_exact(buf).map_err(|error| io_error(error, context()))
+    }
+
+    /// The subsecond of the start time is the onset of the first datarecord.
+    fn read_subsecond(&self, context: impl Fn() -> ErrorContext) -> Result<i64> {
+        let Some(&(offset, size)) = self.layout.annotation_signals.first() else {
+            return Ok(0);
+        };
+
+        let mut bytes = vec![0; size];
+        self.read_at(self.layout.record_offset(0) + (offset as u64), &mut bytes, &context)?;
+        let tals = parse_tals(&bytes).ok_or_else(|| EdfError::FileContainsFormatErrors(context()))?;
+        let onset = tals.first().map_or(0, |tal| tal.onset);
+        Ok(onset.rem_euclid(TIME_DIMENSION))
+    }
+
+    /// Reads the annotations of all datarecords, onsets are relative to the start time
+    /// including its subsecond.
+    fn read_annotations(
+        &self,
+        subsecond: i64,
+        read_annotations: ReadAnnotations,
+        context: impl Fn() -> ErrorContext
+    ) -> Result<Vec<RawAnnotation>> {
+        let mut annotations = Vec::new();
+        if read_annotations == ReadAnnotations::DoNotRead {
+            return Ok(annotations);
+        }
+
+        let mut bytes = Vec::new();
+        for record in 0..self.layout.datarecords {
+            for &(offset, size) in &self.layout.annotation_signals {
+                bytes.resize(size, 0);
+                let offset = self.layout.record_offset(record) + (offset as u64);
+                self.read_at(offset, &mut bytes, &context)?;
+
+                let tals = parse_tals(&bytes).ok_or_else(||
+                    EdfError::FileContainsFormatErrors(context())
+                )?;
+                for tal in tals {
+                    let onset = tal.onset
+                        .checked_sub(subsecond)
+                        .ok_or_else(|| EdfError::FileContainsFormatErrors(context()))?;
+                    for mut text in tal.texts {
+                        let ends = text.eq_ignore_ascii_case("Recording ends");
+                        truncate(&mut text, MAX_ANNOTATION_LEN);
+                        annotations.push(RawAnnotation {
+                            onset,
+                            duration: tal.duration.unwrap_or(-1),
+                            text,
+                        });
+                        if ends && read_annotations == ReadAnnotations::UntilRecordingEnds {
+                            return Ok(annotations);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(annotations)
+    }
+
+    pub(crate) fn samples_in_file(&self, signal: usize) -> i64 {
+        (self.layout.signals[signal].samples_in_datarecord as i64) * self.layout.datarecords
+    }
+
+    /// Reads physical samples of `signal` from its current position.
+    pub(crate) fn read_physical(
+        &self,
+        signal: usize,
+        buf: &mut [f64],
+        context: impl Fn() -> ErrorContext
+    ) -> Result<usize> {
+        let layout = &self.layout;
+        let signal_layout = layout.signals[signal];
+        let per_record = signal_layout.samples_in_datarecord as i64;
+        let samples_in_file = self.samples_in_file(signal);
+
+        let mut state = self.state.lock().unwrap();
+        let State { source, positions, raw } = &mut *state;
+        let start = positions[signal];
+        let count = (buf.len() as i64).min(samples_in_file - start).max(0) as usize;
+
+        let mut done = 0;
+        while done < count {
+            let position = start + (done as i64);
+            let index = (position % per_record) as usize;
+            let n = (signal_layout.samples_in_datarecord - index).min(count - done);
+            let offset =
+                layout.record_offset(position / per_record) +
+                ((signal_layout.offset + index * layout.sample_size) as u64);
+
+            raw.resize(n * layout.sample_size, 0);
+            source.seek(SeekFrom::Start(offset)).map_err(|error| io_error(error, context()))?;
+            source.read_exact(raw).map_err(|error| io_error(error, context()))?;
+            let samples = decode_digital(raw, layout.sample_size);
+            for (target, digital) in buf[done..done + n].iter_mut().zip(samples) {
+                *target = signal_layout.physical(digital);
+            }
+            done += n;
+        }
+
+        positions[signal] = start + (count as i64);
+        Ok(count)
+    }
+
+    pub(crate) fn tell(&self, signal: usize) -> i64 {
+        self.state.lock().unwrap().positions[signal]
+    }
+
+    /// Moves the position of `signal`, clamped to the samples in the file.
+    pub(crate) fn seek(&self, signal: usize, position: i64) -> i64 {
+        let position = position.clamp(0, self.samples_in_file(signal));
+        self.state.lock().unwrap().positions[signal] = position;
+        position
+    }
+}
+
+/// Truncates to at most `max` bytes without splitting a character.
+fn truncate(text: &mut String, max: usize) {
+    if text.len() > max {
+        let end = (0..=max).rev().find(|&end| text.is_char_boundary(end)).unwrap_or(0);
+        text.truncate(end);
+    }
+}
@@ -0,0 +1,108 @@
+//! Time-stamped Annotation Lists, the content of the EDF+/BDF+ annotation signals.
+//!
+//! A TAL is `+onset[0x15 duration]0x14[text 0x14]...0x00`. The first TAL of the first
+//! annotation signal of every datarecord holds the onset of the datarecord and no text.
+
+use super::TIME_DIMENSION;
+
+const ONSET_END: u8 = 0x14;
+const DURATION_START: u8 = 0x15;
+const TAL_END: u8 = 0x00;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Tal {
+    /// Onset in units of 100 ns.
+    pub onset: i64,
+    /// Duration in units of 100 ns.
+    pub duration: Option<i64>,
+    pub texts: Vec<String>,
+}
+
+/// Parses a decimal number of seconds, e.g. `+12.5`, into units of 100 ns. Digits beyond the
+/// 100 ns resolution are truncated.
+pub(crate) fn parse_ticks(text: &str, sign_required: bool) -> Option<i64> {
+    let (negative, digits) = match text.as_bytes().first()? {
+        b'+' => (false, &text[1..]),
+        b'-' => (true, &text[1..]),
+        _ if sign_required => {
+            return None;
+        }
+        _ => (false, text),
+    };
+    let (seconds, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let digit = |c: char| c.is_ascii_digit();
+    if seconds.is_empty() || !seconds.chars().all(digit) || !fraction.chars().all(digit) {
+        return None;
+    }
+
+    let mut ticks = seconds.parse::<i64>().ok()?.checked_mul(TIME_DIMENSION)?;
+    let mut scale = TIME_DIMENSION / 10;
+    for c in fraction.bytes().take(7) {
+        ticks = ticks.checked_add(((c - b'0') as i64) * scale)?;
+        scale /= 10;
+    }
+    Some(if negative { -ticks } else { ticks })
+}
+
+/// Parses the TALs of one annotation signal of one datarecord, `None` on format errors.
+pub(crate) fn parse_tals(bytes: &[u8]) -> Option<Vec<Tal>> {
+    let mut tals = Vec::new();
+    // everything after the last TAL is padding
+    for tal in bytes.split(|&c| c == TAL_END).filter(|tal| !tal.is_empty()) {
+        let mut parts = tal.split(|&c| c == ONSET_END);
+        let timing = std::str::from_utf8(parts.next()?).ok()?;
+        // a TAL ends with 0x14, which leaves an empty part behind
+        if parts.next_back()? != b"" {
+            return None;
+        }
+
+        let (onset, duration) = match timing.split_once(DURATION_START as char) {
+            Some((onset, duration)) => (onset, Some(parse_ticks(duration, false)?)),
+            None => (timing, None),
+        };
+        tals.push(Tal {
+            onset: parse_ticks(onset, true)?,
+            duration,
+            texts: parts
+                .filter(|text| !text.is_empty())
+                .map(|text| String::from_utf8_lossy(text).into_owned())
+                .collect(),
+        });
+    }
+    Some(tals)
+}
+
+/// The time-keeping TAL of a datarecord starting at `onset` in units of 100 ns, as EDFlib
+/// writes it: the seconds with a fraction of 7 digits, which is left out if every datarecord
+/// of the file starts at a whole second.
+pub(crate) fn encode_timekeeping(onset: i64, whole_seconds: bool) -> Vec<u8> {
+    let mut text = format!("+{}", onset / TIME_DIMENSION);
+    if !whole_seconds {
+        text.push_str(&format!(".{:07}", onset % TIME_DIMENSION));
+    }
+    let mut bytes = text.into_bytes();
+    bytes.extend([ONSET_END, ONSET_END, TAL_END]);
+    bytes
+}
+
+/// The TAL of one annotation as EDFlib writes it, without the closing 0x00. `onset` and
+/// `duration` are in microseconds and not negative, their fractions have 6 digits.
+pub(crate) fn encode_annotation(onset: i64, duration: Option<i64>, text: &str) -> Vec<u8> {
+    let seconds = |micros: i64| {
+        let mut seconds = (micros / 1_000_000).to_string();
+        if micros % 1_000_000 != 0 {
+            seconds.push_str(&format!(".{:06}", micros % 1_000_000));
+        }
+        seconds
+    };
+
+    let mut bytes = format!("+{}", seconds(onset)).into_bytes();
+    if let Some(duration) = duration {
+        bytes.push(DURATION_START);
+        bytes.extend(seconds(duration).into_bytes());
+    }
+    bytes.push(ONSET_END);
+    bytes.extend_from_slice(text.as_bytes());
+    bytes.push(ONSET_END);
+    bytes
+}
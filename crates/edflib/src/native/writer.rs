@@ -0,0 +1,571 @@
+use std::{
+    fs::File,
+    io::{ Seek, SeekFrom, Write },
+    path::PathBuf,
+    time::Duration,
+};
+
+use super::{ header::*, tal::*, HEADER_BLOCK, TIME_DIMENSION };
+use crate::{
+    builder::{ EdfWriterBuilder, SignalSpec },
+    error::*,
+    header::StartDateTime,
+    info::*,
+    utils::{ check_digital, truncate_annotation },
+    AnnotationPosition,
+    Filetype,
+};
+
+/// Bytes of every annotation signal in a datarecord, `EDFLIB_ANNOTATION_BYTES`.
+const ANNOTATION_BYTES: usize = 120;
+/// `EDFLIB_MAX_ANNOTATION_CHANNELS`
+const MAX_ANNOTATION_SIGNALS: usize = 64;
+/// Offset of the number of datarecords in the header, it is written when the file is finished.
+const DATARECORDS_OFFSET: u64 = 236;
+/// From this many datarecords on EDFlib leaves the number of datarecords in the header at -1.
+const MAX_DATARECORDS: i64 = 100_000_000;
+
+/// Everything the native writer needs to know before the header is written, as validated by
+/// [`crate::EdfWriterBuilder`].
+pub(crate) struct WriterSpec {
+    /// `EDFPLUS` or `BDFPLUS`.
+    pub filetype: Filetype,
+    pub signals: Vec<SignalSpec>,
+    pub samples_per_record: Vec<i32>,
+    pub datarecord_duration: Duration,
+    pub patient: PatientInfo,
+    pub recording: RecordingInfo,
+    pub start: StartDateTime,
+    pub annotation_signals: usize,
+    pub annotation_position: AnnotationPosition,
+}
+
+/// An annotation as EDFlib keeps it until the file is finished.
+struct PendingAnnotation {
+    /// Onset in microseconds, the subsecond of the start time included.
+    onset: i64,
+    /// Duration in microseconds.
+    duration: Option<i64>,
+    text: String,
+}
+
+/// A signal in the order of the file.
+enum Column<'a> {
+    Data(&'a SignalSpec, usize),
+    Annotation,
+}
+
+/// EDF+/BDF+ writer implemented in Rust, created by [`crate::EdfWriterBuilder::build_native`].
+///
+/// The file has the same bytes EDFlib writes for the same settings: the time-keeping TAL at
+/// the start of the first annotation signal of every datarecord, one annotation per annotation
+/// signal and datarecord written when the file is finished, and the number of datarecords
+/// patched into the header last. Unlike [`crate::Edf`] it does not use the global handle table
+/// of the C library, so any number of files can be written at the same time.
+pub struct EdfWriter<W: Write + Seek = File> {
+    /// Taken by [`EdfWriter::finish`].
+    sink: Option<W>,
+    /// Position of the header in the sink, the positions in the file are relative to it.
+    base: u64,
+    path: Option<PathBuf>,
+    filetype: Filetype,
+    layout: Layout,
+    /// In units of 100 ns.
+    datarecord_duration: i64,
+    /// The subsecond of the start time in units of 100 ns, added to every onset in the file.
+    subsecond: i64,
+    /// The datarecord being written.
+    record: Vec<u8>,
+    /// The signal the next per-signal write belongs to.
+    next_signal: usize,
+    annotations: Vec<PendingAnnotation>,
+    finished: bool,
+}
+
+impl<W: Write + Seek> EdfWriter<W> {
+    /// Writes the header at the current position of `sink` and returns a writer positioned at
+    /// the first datarecord.
+    pub(crate) fn new(mut sink: W, spec: WriterSpec, path: Option<PathBuf>) -> Result<Self> {
+        let context = || {
+            let context = ErrorContext::new("open");
+            match &path {
+                Some(path) => context.path(path),
+                None => context,
+            }
+        };
+        if !(1..=MAX_ANNOTATION_SIGNALS).contains(&spec.annotation_signals) {
+            return Err(
+                EdfError::InvalidArgument(
+                    format!("1 to {} annotation signals are supported", MAX_ANNOTATION_SIGNALS),
+                    context()
+                )
+            );
+        }
+
+        let mut columns: Vec<Column> = spec.signals
+            .iter()
+            .zip(&spec.samples_per_record)
+            .map(|(signal, &samples)| Column::Data(signal, samples.max(1) as usize))
+            .collect();
+        let at = match spec.annotation_position {
+            AnnotationPosition::Start => 0,
+            AnnotationPosition::Middle => columns.len() / 2,
+            AnnotationPosition::End => columns.len(),
+        };
+        columns.splice(at..at, (0..spec.annotation_signals).map(|_| Column::Annotation));
+
+        let bdf = spec.filetype == Filetype::BDFPLUS;
+        let sample_size = if bdf { 3 } else { 2 };
+        let (limit_min, limit_max) = spec.filetype.digital_limits();
+        let mut layout = Layout {
+            header_size: (HEADER_BLOCK * (columns.len() + 1)) as u64,
+            record_size: 0,
+            datarecords: 0,
+            sample_size,
+            signals: Vec::new(),
+            annotation_signals: Vec::new(),
+        };
+        for column in &columns {
+            let offset = layout.record_size;
+            match *column {
+                Column::Data(signal, samples) => {
+                    let (physical_minimum, physical_maximum) = signal.physical_range;
+                    let (digital_minimum, digital_maximum) = signal.digital_range;
+                    let gain =
+                        (physical_maximum - physical_minimum) /
+                        ((digital_maximum as f64) - (digital_minimum as f64));
+                    layout.signals.push(SignalLayout {
+                        offset,
+                        samples_in_datarecord: samples,
+                        digital_minimum,
+                        digital_maximum,
+                        gain,
+                        shift: physical_maximum / gain - (digital_maximum as f64),
+                    });
+                    layout.record_size += samples * sample_size;
+                }
+                Column::Annotation => {
+                    layout.annotation_signals.push((offset, ANNOTATION_BYTES));
+                    layout.record_size += ANNOTATION_BYTES;
+                }
+            }
+        }
+
+        let start = &spec.start;
+        let mut header = Vec::with_capacity(layout.header_size as usize);
+        if bdf {
+            header.push(0xff);
+            push_field(&mut header, "BIOSEMI", 7);
+        } else {
+            push_field(&mut header, "0", 8);
+        }
+        push_field(&mut header, &format_patient(&spec.patient), 80);
+        push_field(&mut header, &format_recording(&spec.recording, start), 80);
+        let date = format!("{:02}.{:02}.{:02}", start.day, start.month, start.year % 100);
+        push_field(&mut header, &date, 8);
+        let time = format!("{:02}.{:02}.{:02}", start.hour, start.minute, start.second);
+        push_field(&mut header, &time, 8);
+        push_field(&mut header, &layout.header_size.to_string(), 8);
+        push_field(&mut header, if bdf { "BDF+C" } else { "EDF+C" }, 44);
+        push_field(&mut header, "-1", 8);
+        let datarecord_duration = (spec.datarecord_duration.as_nanos() / 100) as i64;
+        let duration = if datarecord_duration == TIME_DIMENSION {
+            "1".to_string()
+        } else {
+            format_number((datarecord_duration as f64) / (TIME_DIMENSION as f64))
+        };
+        push_field(&mut header, &duration, 8);
+        push_field(&mut header, &columns.len().to_string(), 4);
+
+        // the signal headers are stored column by column
+        let annotation_label = if bdf { "BDF Annotations" } else { "EDF Annotations" };
+        let rows: Vec<[String; 10]> = columns
+            .iter()
+            .map(|column| {
+                match *column {
+                    Column::Data(signal, samples) =>
+                        [
+                            // EDFlib drops the spaces around every text
+                            signal.label.trim_matches(' ').to_string(),
+                            signal.transducer.trim_matches(' ').to_string(),
+                            signal.unit.trim_matches(' ').to_string(),
+                            format_number(signal.physical_range.0),
+                            format_number(signal.physical_range.1),
+                            signal.digital_range.0.to_string(),
+                            signal.digital_range.1.to_string(),
+                            signal.prefilter.trim_matches(' ').to_string(),
+                            samples.to_string(),
+                            String::new(),
+                        ],
+                    Column::Annotation =>
+                        [
+                            annotation_label.to_string(),
+                            String::new(),
+                            String::new(),
+                            "-1".to_string(),
+                            "1".to_string(),
+                            limit_min.to_string(),
+                            limit_max.to_string(),
+                            String::new(),
+                            (ANNOTATION_BYTES / sample_size).to_string(),
+                            String::new(),
+                        ],
+                }
+            })
+            .collect();
+        for (field, len) in [16, 80, 8, 8, 8, 8, 8, 80, 8, 32].into_iter().enumerate() {
+            for row in &rows {
+                push_field(&mut header, &row[field], len);
+            }
+        }
+
+        let base = sink.stream_position().map_err(|_| EdfError::FileWriteError(context()))?;
+        sink.write_all(&header).map_err(|_| EdfError::FileWriteError(context()))?;
+
+        Ok(EdfWriter {
+            sink: Some(sink),
+            base,
+            path,
+            filetype: spec.filetype,
+            record: vec![0; layout.record_size],
+            layout,
+            datarecord_duration,
+            subsecond: (start.subsecond.subsec_nanos() / 100) as i64,
+            next_signal: 0,
+            annotations: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Writes the file configured by `builder` into any seekable sink, e.g. a
+    /// `Cursor<Vec<u8>>`. The path of the builder is not used, set the type of the file with
+    /// [`EdfWriterBuilder::filetype`] unless the path has the right extension.
+    ///
+    /// The file starts at the current position of the sink, bytes before it are left alone.
+    /// The header is finished in the sink when the writer is finished or dropped, take the
+    /// sink back with [`EdfWriter::finish`].
+    pub fn to_writer(sink: W, builder: EdfWriterBuilder) -> Result<Self> {
+        let spec = builder.writer_spec()?;
+        EdfWriter::new(sink, spec, None)
+    }
+
+    fn context(&self, operation: &'static str) -> ErrorContext {
+        let context = ErrorContext::new(operation);
+        match &self.path {
+            Some(path) => context.path(path),
+            None => context,
+        }
+    }
+
+    fn sink(&mut self) -> &mut W {
+        // only `finish` takes the sink, and it consumes the writer
+        self.sink.as_mut().unwrap()
+    }
+
+    /// `EDFPLUS` or `BDFPLUS`.
+    pub fn filetype(&self) -> Filetype {
+        self.filetype
+    }
+
+    /// Number of samples of every signal per datarecord.
+    pub fn samples_per_record(&self) -> Vec<usize> {
+        self.layout.signals
+            .iter()
+            .map(|signal| signal.samples_in_datarecord)
+            .collect()
+    }
+
+    /// Number of complete datarecords written so far.
+    pub fn datarecords(&self) -> i64 {
+        self.layout.datarecords
+    }
+
+    /// Checks `len` against the samples per datarecord of the signal expected next and returns
+    /// that signal.
+    fn check_next_signal(&self, operation: &'static str, len: usize) -> Result<usize> {
+        let signal = self.next_signal;
+        let context = || self.context(operation).signal(signal as i32);
+        if self.finished {
+            return Err(EdfError::Rejected(context()));
+        }
+
+        let expected = self.layout.signals[signal].samples_in_datarecord;
+        if expected != len {
+            return Err(
+                EdfError::InvalidArgument(
+                    format!("expected {} samples, got {}", expected, len),
+                    context()
+                )
+            );
+        }
+        Ok(signal)
+    }
+
+    /// Encodes digital samples of `signal` into the current datarecord and writes the
+    /// datarecord once every signal is in.
+    fn put(&mut self, signal: usize, samples: impl Iterator<Item = i32>) -> Result<()> {
+        let layout = self.layout.signals[signal];
+        let sample_size = self.layout.sample_size;
+        let bytes = &mut self.record[layout.offset..][..layout.samples_in_datarecord * sample_size];
+        for (sample, digital) in bytes.chunks_exact_mut(sample_size).zip(samples) {
+            encode_digital(digital.clamp(layout.digital_minimum, layout.digital_maximum), sample);
+        }
+
+        self.next_signal = (self.next_signal + 1) % self.layout.signals.len();
+        if self.next_signal == 0 {
+            self.write_record()?;
+        }
+        Ok(())
+    }
+
+    /// The time-keeping TAL of `record`, the onset of the datarecord.
+    fn timekeeping(&self, record: i64) -> Vec<u8> {
+        let onset = record * self.datarecord_duration + self.subsecond;
+        let whole_seconds = self.datarecord_duration % TIME_DIMENSION == 0 && self.subsecond == 0;
+        encode_timekeeping(onset, whole_seconds)
+    }
+
+    /// Clears the annotation signals of the current datarecord and puts in its time-keeping
+    /// TAL. Like in EDFlib the annotation signals are next to each other.
+    fn fill_annotation_signals(&mut self) {
+        let (offset, _) = self.layout.annotation_signals[0];
+        let size: usize = self.layout.annotation_signals
+            .iter()
+            .map(|&(_, size)| size)
+            .sum();
+        let timekeeping = self.timekeeping(self.layout.datarecords);
+        let annotations = &mut self.record[offset..offset + size];
+        annotations.fill(0);
+        annotations[..timekeeping.len()].copy_from_slice(&timekeeping);
+    }
+
+    fn write_record(&mut self) -> Result<()> {
+        self.fill_annotation_signals();
+        let record = std::mem::take(&mut self.record);
+        let result = self.sink().write_all(&record);
+        self.record = record;
+        result.map_err(|_| EdfError::FileWriteError(self.context("write_record")))?;
+        self.layout.datarecords += 1;
+        Ok(())
+    }
+
+    /// Writes one datarecord of physical samples for the next signal.
+    ///
+    /// The signals are expected in order, 0, 1, .., n - 1, 0, 1, .., and `samples` has to hold
+    /// exactly the samples per datarecord of that signal.
+    pub fn write_samples(&mut self, samples: impl AsRef<[f64]>) -> Result<()> {
+        let samples = samples.as_ref();
+        let signal = self.check_next_signal("write_samples", samples.len())?;
+        let layout = self.layout.signals[signal];
+        self.put(
+            signal,
+            samples.iter().map(|&sample| layout.digital(sample))
+        )
+    }
+
+    /// Writes one datarecord of digital samples for the next signal.
+    ///
+    /// Every sample has to fit the type of the file, samples outside the digital minimum and
+    /// maximum of the signal are clamped to them like EDFlib does.
+    pub fn write_digital_samples(&mut self, samples: &[i32]) -> Result<()> {
+        let signal = self.check_next_signal("write_digital_samples", samples.len())?;
+        for &sample in samples {
+            check_digital(self.filetype, sample, || {
+                self.context("write_digital_samples").signal(signal as i32)
+            })?;
+        }
+        self.put(signal, samples.iter().copied())
+    }
+
+    /// Adds an annotation, `text` is stored as UTF-8. Like EDFlib the first 40 bytes are kept,
+    /// longer texts are cut at the last character boundary before that, and control characters
+    /// are written as `.`.
+    ///
+    /// `onset` is relative to the start of the recording, both times are written with microsecond
+    /// resolution like the C library does. Use `None` for events without a duration.
+    ///
+    /// The annotations are stored when the file is finished, one per annotation signal and
+    /// datarecord. Like EDFlib, annotations that do not fit are dropped with a warning in the
+    /// log, and a file without datarecords gets one datarecord of annotations only per
+    /// annotation.
+    pub fn write_annotation(
+        &mut self,
+        onset: Duration,
+        duration: Option<Duration>,
+        text: impl AsRef<str>
+    ) -> Result<()> {
+        let context = || self.context("write_annotation");
+        let invalid = |message: String| Err(EdfError::InvalidArgument(message, context()));
+
+        if self.finished {
+            return Err(EdfError::Rejected(context()));
+        }
+        let text = truncate_annotation(text.as_ref());
+        if text.contains('\0') {
+            return Err(EdfError::InteriorNul(context()));
+        }
+
+        let micros = |time: Duration| i64::try_from(time.as_micros()).ok();
+        let subsecond = self.subsecond / 10;
+        let Some(onset) = micros(onset).and_then(|onset| onset.checked_add(subsecond)) else {
+            return invalid(format!("onset {:?} is out of range", onset));
+        };
+        let duration = match duration {
+            None => None,
+            Some(duration) =>
+                match micros(duration) {
+                    Some(micros) => Some(micros),
+                    None => {
+                        return invalid(format!("duration {:?} is out of range", duration));
+                    }
+                }
+        };
+
+        self.annotations.push(PendingAnnotation {
+            onset,
+            duration,
+            text: text
+                .chars()
+                .map(|c| if c < ' ' { '.' } else { c })
+                .collect(),
+        });
+        Ok(())
+    }
+
+    /// Stores every annotation in its annotation signal, one per annotation signal and
+    /// datarecord, and returns how many fit. The first annotation signal of a datarecord starts
+    /// with the time-keeping TAL again.
+    fn write_annotations(&mut self) -> Result<usize> {
+        let annotation_signals = self.layout.annotation_signals.clone();
+        let annotations = std::mem::take(&mut self.annotations);
+
+        let mut written = 0;
+        for (index, annotation) in annotations.iter().enumerate() {
+            let record = (index / annotation_signals.len()) as i64;
+            if record >= self.layout.datarecords {
+                break;
+            }
+            let (offset, size) = annotation_signals[index % annotation_signals.len()];
+            let mut bytes = Vec::with_capacity(size);
+            if index % annotation_signals.len() == 0 {
+                bytes = self.timekeeping(record);
+            }
+            bytes.extend(
+                encode_annotation(annotation.onset, annotation.duration, &annotation.text)
+            );
+            bytes.resize(size, 0);
+
+            let position = self.layout.record_offset(record) + (offset as u64);
+            if let Err(error) = self.write_at(position, &bytes) {
+                self.annotations = annotations;
+                return Err(error);
+            }
+            written += 1;
+        }
+        self.annotations = annotations;
+        Ok(written)
+    }
+
+    /// Completes the last datarecord, writes the annotations and the number of datarecords.
+    /// Later calls do nothing.
+    fn close(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+
+        // EDFlib writes every signal as it comes, the signals of an incomplete datarecord stay
+        // in the file but are not counted
+        if self.next_signal != 0 {
+            let last = self.layout.signals[self.next_signal - 1];
+            let end = last.offset + last.samples_in_datarecord * self.layout.sample_size;
+            self.fill_annotation_signals();
+            let record = std::mem::take(&mut self.record);
+            let result = self.sink().write_all(&record[..end]);
+            self.record = record;
+            result.map_err(|_| EdfError::FileWriteError(self.context("finish")))?;
+        }
+
+        if self.layout.datarecords == 0 && !self.annotations.is_empty() {
+            self.write_annotation_records()?;
+        }
+        let written = self.write_annotations()?;
+        if written < self.annotations.len() {
+            log::warn!(
+                "{}: only {} of {} annotations fit in {} datarecords, the others are dropped",
+                self.context("finish"),
+                written,
+                self.annotations.len(),
+                self.layout.datarecords
+            );
+        }
+        if self.layout.datarecords < MAX_DATARECORDS {
+            // overwrites the "-1" of the field, at least two characters of it
+            let datarecords = format!("{:<2}", self.layout.datarecords);
+            self.write_at(DATARECORDS_OFFSET, datarecords.as_bytes())?;
+        }
+        let sink = self.sink();
+        let result = sink.seek(SeekFrom::End(0)).and_then(|_| sink.flush());
+        result.map_err(|_| EdfError::FileWriteError(self.context("finish")))
+    }
+
+    /// Without datarecords EDFlib writes one datarecord per annotation that holds only the
+    /// annotation signals, right after the header and over an incomplete datarecord. The
+    /// annotations then go where they would be in complete datarecords, past the end of these.
+    fn write_annotation_records(&mut self) -> Result<()> {
+        let size: usize = self.layout.annotation_signals
+            .iter()
+            .map(|&(_, size)| size)
+            .sum();
+        for record in 0..self.annotations.len() as i64 {
+            let mut bytes = self.timekeeping(record);
+            bytes.resize(size, 0);
+            let position = self.layout.header_size + (record as u64) * (size as u64);
+            self.write_at(position, &bytes)?;
+        }
+        self.layout.datarecords = self.annotations.len() as i64;
+        Ok(())
+    }
+
+    /// Writes `bytes` at `position` in the file, past whatever the sink held before the header.
+    fn write_at(&mut self, position: u64, bytes: &[u8]) -> Result<()> {
+        let position = self.base + position;
+        let sink = self.sink();
+        let result = sink.seek(SeekFrom::Start(position)).and_then(|_| sink.write_all(bytes));
+        result.map_err(|_| EdfError::FileWriteError(self.context("finish")))
+    }
+
+    /// Finishes the file and returns the sink. Annotations that do not fit are dropped with a
+    /// warning, so only a failing sink gives an error. Dropping the writer finishes it as well,
+    /// but can only log errors.
+    pub fn finish(mut self) -> Result<W> {
+        self.close()?;
+        Ok(self.sink.take().unwrap())
+    }
+}
+
+impl<W: Write + Seek> Drop for EdfWriter<W> {
+    fn drop(&mut self) {
+        if let Err(error) = self.close() {
+            log::error!("{}", error);
+        }
+    }
+}
+
+impl StartDateTime {
+    /// The current local time, which EDFlib takes as the start of a recording when none is set.
+    pub(crate) fn now() -> Self {
+        use chrono::{ Datelike, Timelike };
+
+        let now = chrono::Local::now();
+        StartDateTime {
+            year: now.year(),
+            month: now.month() as i32,
+            day: now.day() as i32,
+            hour: now.hour() as i32,
+            minute: now.minute() as i32,
+            second: now.second() as i32,
+            subsecond: Duration::ZERO,
+        }
+    }
+}
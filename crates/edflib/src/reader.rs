@@ -0,0 +1,259 @@
+#[cfg(feature = "ffi")]
+use std::{ marker::PhantomData, os::raw::c_int, sync::Mutex };
+#[cfg(feature = "pure-rust")]
+use std::io::{ Cursor, Read, Seek };
+use std::path::{ Path, PathBuf };
+
+#[cfg(feature = "ffi")]
+use edflib_sys::*;
+
+#[cfg(feature = "pure-rust")]
+use crate::native::NativeReader;
+use crate::{ annotation::*, error::*, header::*, signal::SignalReader };
+#[cfg(feature = "ffi")]
+use crate::{ ffi, utils::*, Filetype };
+
+/// Where the samples and annotations of an [`EdfReader`] come from.
+enum Backend<'s> {
+    /// A handle of the C library. The position of every signal lives in the C library, so calls
+    /// are serialized like those of the native backend. It reads from a path and borrows nothing.
+    #[cfg(feature = "ffi")]
+    Ffi(Mutex<c_int>, PhantomData<&'s ()>),
+    #[cfg(feature = "pure-rust")]
+    Native(NativeReader<'s>),
+}
+
+/// A file opened for reading. The handle is closed when the reader is dropped.
+///
+/// `'s` is the lifetime of the source the file is read from, `'static` unless it borrows, e.g.
+/// the bytes given to [`EdfReader::from_bytes`].
+pub struct EdfReader<'s> {
+    /// `None` when read from memory or a stream.
+    path: Option<PathBuf>,
+    backend: Backend<'s>,
+    header: Header,
+}
+
+impl EdfReader<'static> {
+    /// Opens a file and reads all of its annotations.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with(path, ReadAnnotations::All)
+    }
+
+    /// Opens a file with the C library, or with the native backend when the crate is built
+    /// without the `ffi` feature.
+    pub fn open_with<P: AsRef<Path>>(path: P, read_annotations: ReadAnnotations) -> Result<Self> {
+        #[cfg(feature = "ffi")]
+        {
+            Self::open_ffi(path.as_ref(), read_annotations)
+        }
+        #[cfg(not(feature = "ffi"))]
+        {
+            Self::open_native(path, read_annotations)
+        }
+    }
+
+    #[cfg(feature = "ffi")]
+    fn open_ffi(path: &Path, read_annotations: ReadAnnotations) -> Result<Self> {
+        let path = path.to_path_buf();
+        let context = || ErrorContext::new("open").path(&path);
+        let path_c = path_to_cstring(&path, context)?;
+
+        // edflib_hdr_t is close to 1 MB, keep it off the stack
+        let mut hdr = Box::<edflib_hdr_t>::new_uninit();
+        let result = unsafe {
+            ffi::open_file_readonly(path_c.as_ptr(), hdr.as_mut_ptr(), read_annotations.to_raw())
+        };
+        let hdr = unsafe { hdr.assume_init() };
+
+        if result < 0 {
+            // the error code is reported in the filetype field
+            return Err(EdfError::from_code(hdr.filetype, context()));
+        }
+
+        let filetype = match Filetype::from_raw(hdr.filetype) {
+            Some(filetype) => filetype,
+            None => {
+                unsafe { ffi::close_file(hdr.handle) };
+                return Err(EdfError::FiletypeError(context()));
+            }
+        };
+
+        Ok(EdfReader {
+            path: Some(path),
+            backend: Backend::Ffi(Mutex::new(hdr.handle), PhantomData),
+            header: Header::from_raw(&hdr, filetype),
+        })
+    }
+
+    /// Opens a file with the native Rust parser instead of the C library.
+    #[cfg(feature = "pure-rust")]
+    pub fn open_native<P: AsRef<Path>>(path: P, read_annotations: ReadAnnotations) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let context = || ErrorContext::new("open").path(&path);
+        let file = std::fs::File
+            ::open(&path)
+            .map_err(|error| crate::native::io_error(error, context()))?;
+        Self::open_source(Box::new(std::io::BufReader::new(file)), read_annotations, Some(path))
+    }
+
+}
+
+impl<'s> EdfReader<'s> {
+    /// Reads a file from any seekable source with the native backend, e.g. an entry of an
+    /// archive. The source is read on demand, wrap it in a `BufReader` if its reads are slow.
+    #[cfg(feature = "pure-rust")]
+    pub fn from_reader<R: Read + Seek + Send + 's>(
+        reader: R,
+        read_annotations: ReadAnnotations
+    ) -> Result<Self> {
+        Self::open_source(Box::new(reader), read_annotations, None)
+    }
+
+    /// Reads a file held in memory with the native backend. The bytes are borrowed, not
+    /// copied, for as long as the reader lives.
+    #[cfg(feature = "pure-rust")]
+    pub fn from_bytes(bytes: &'s [u8], read_annotations: ReadAnnotations) -> Result<Self> {
+        Self::from_reader(Cursor::new(bytes), read_annotations)
+    }
+
+    #[cfg(feature = "pure-rust")]
+    fn open_source(
+        source: Box<dyn crate::native::Source + 's>,
+        read_annotations: ReadAnnotations,
+        path: Option<PathBuf>
+    ) -> Result<Self> {
+        let context = || {
+            let context = ErrorContext::new("open");
+            match &path {
+                Some(path) => context.path(path),
+                None => context,
+            }
+        };
+        let (native, header) = NativeReader::open(source, read_annotations, context)?;
+
+        Ok(EdfReader {
+            path,
+            backend: Backend::Native(native),
+            header,
+        })
+    }
+
+    pub(crate) fn context(&self, operation: &'static str) -> ErrorContext {
+        let context = ErrorContext::new(operation);
+        match &self.path {
+            Some(path) => context.path(path),
+            None => context,
+        }
+    }
+
+    /// Reads physical samples of `signal` from its current position.
+    pub(crate) fn read_physical(&self, signal: usize, buf: &mut [f64]) -> Result<usize> {
+        let context = || self.context("read_physical_samples").signal(signal as i32);
+        match &self.backend {
+            #[cfg(feature = "ffi")]
+            Backend::Ffi(hdl, _) => {
+                let n = buf.len().min(c_int::MAX as usize) as c_int;
+                let hdl = hdl.lock().unwrap();
+                let result = unsafe {
+                    edfread_physical_samples(*hdl, signal as c_int, n, buf.as_mut_ptr())
+                };
+                EdfError::check(result, context).map(|n| n as usize)
+            }
+            #[cfg(feature = "pure-rust")]
+            Backend::Native(native) => native.read_physical(signal, buf, context),
+        }
+    }
+
+    /// The position of `signal` in samples.
+    pub(crate) fn tell(&self, signal: usize) -> Result<u64> {
+        let result = match &self.backend {
+            #[cfg(feature = "ffi")]
+            Backend::Ffi(hdl, _) => unsafe { edftell(*hdl.lock().unwrap(), signal as c_int) },
+            #[cfg(feature = "pure-rust")]
+            Backend::Native(native) => native.tell(signal),
+        };
+        if result < 0 {
+            return Err(EdfError::Rejected(self.context("tell").signal(signal as i32)));
+        }
+        Ok(result as u64)
+    }
+
+    /// Moves the position of `signal`, past the end stops at the end.
+    pub(crate) fn seek(&self, signal: usize, position: i64) -> Result<u64> {
+        let result = match &self.backend {
+            #[cfg(feature = "ffi")]
+            Backend::Ffi(hdl, _) => unsafe {
+                edfseek(*hdl.lock().unwrap(), signal as c_int, position, EDFSEEK_SET as c_int)
+            },
+            #[cfg(feature = "pure-rust")]
+            Backend::Native(native) => native.seek(signal, position),
+        };
+        if result < 0 {
+            return Err(EdfError::Rejected(self.context("seek").signal(signal as i32)));
+        }
+        Ok(result as u64)
+    }
+
+    /// The annotation at `index`, `0..header().annotations_in_file`.
+    pub(crate) fn annotation(&self, index: usize) -> Result<Annotation> {
+        let context = || self.context("get_annotation");
+        match &self.backend {
+            #[cfg(feature = "ffi")]
+            Backend::Ffi(hdl, _) => Annotation::read_raw(*hdl.lock().unwrap(), index, context),
+            #[cfg(feature = "pure-rust")]
+            Backend::Native(native) =>
+                match native.annotations().get(index) {
+                    Some(annotation) => annotation.to_annotation(context),
+                    None => Err(EdfError::Rejected(context())),
+                },
+        }
+    }
+
+    /// The path the file was opened from, `None` for [`EdfReader::from_reader`] and
+    /// [`EdfReader::from_bytes`].
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn signals(&self) -> &[SignalInfo] {
+        &self.header.signals
+    }
+
+    pub fn number_of_signals(&self) -> usize {
+        self.header.signals.len()
+    }
+
+    /// A reader for the samples of `signal`.
+    pub fn signal(&self, signal: usize) -> Result<SignalReader<'_, 's>> {
+        if signal >= self.number_of_signals() {
+            return Err(
+                EdfError::InvalidArgument(
+                    format!("file has {} signals", self.number_of_signals()),
+                    self.context("signal").signal(signal as i32)
+                )
+            );
+        }
+        Ok(SignalReader::new(self, signal))
+    }
+
+    /// The annotations read when the file was opened, see [`ReadAnnotations`].
+    pub fn annotations(&self) -> Annotations<'_, 's> {
+        Annotations::new(self)
+    }
+}
+
+impl Drop for EdfReader<'_> {
+    fn drop(&mut self) {
+        #[cfg(feature = "ffi")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Ffi(hdl, _) = &mut self.backend {
+            let hdl = *hdl.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+            unsafe { ffi::close_file(hdl) };
+        }
+    }
+}
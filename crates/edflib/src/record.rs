@@ -0,0 +1,137 @@
+use std::os::raw::{ c_int, c_short, c_void };
+
+use edflib_sys::*;
+
+use crate::Filetype;
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for f64 {}
+    impl Sealed for i32 {}
+    impl Sealed for i16 {}
+    impl Sealed for [u8; 3] {}
+}
+
+/// Sample types a whole datarecord can be written as with the `edf_blockwrite_*` functions:
+///
+/// - `f64`: physical values, `edf_blockwrite_physical_samples`
+/// - `i32`: digital values, `edf_blockwrite_digital_samples`
+/// - `i16`: digital values for EDF only, `edf_blockwrite_digital_short_samples`
+/// - `[u8; 3]`: little endian 24 bit digital values for BDF only, written as they are,
+///   `edf_blockwrite_digital_3byte_samples`
+///
+/// EDFlib clamps `i32` and `i16` values to the digital minimum and maximum of their signal.
+pub trait RecordSample: sealed::Sealed + Copy + Default {
+    #[doc(hidden)]
+    const OPERATION: &'static str;
+
+    #[doc(hidden)]
+    fn supports(filetype: Filetype) -> bool;
+
+    /// # Safety
+    /// `buf` must hold the samples of a complete datarecord.
+    #[doc(hidden)]
+    unsafe fn blockwrite(hdl: c_int, buf: &[Self]) -> c_int;
+}
+
+impl RecordSample for f64 {
+    const OPERATION: &'static str = "blockwrite_physical_samples";
+
+    fn supports(_: Filetype) -> bool {
+        true
+    }
+
+    unsafe fn blockwrite(hdl: c_int, buf: &[Self]) -> c_int {
+        // EDFlib only reads from the buffer
+        unsafe { edf_blockwrite_physical_samples(hdl, buf.as_ptr().cast_mut()) }
+    }
+}
+
+impl RecordSample for i32 {
+    const OPERATION: &'static str = "blockwrite_digital_samples";
+
+    fn supports(_: Filetype) -> bool {
+        true
+    }
+
+    unsafe fn blockwrite(hdl: c_int, buf: &[Self]) -> c_int {
+        // EDFlib only reads from the buffer
+        unsafe { edf_blockwrite_digital_samples(hdl, buf.as_ptr().cast_mut()) }
+    }
+}
+
+impl RecordSample for i16 {
+    const OPERATION: &'static str = "blockwrite_digital_short_samples";
+
+    fn supports(filetype: Filetype) -> bool {
+        matches!(filetype, Filetype::EDF | Filetype::EDFPLUS)
+    }
+
+    unsafe fn blockwrite(hdl: c_int, buf: &[Self]) -> c_int {
+        // EDFlib clamps the values in place, so it gets a copy
+        let mut buf = buf.to_vec();
+        unsafe { edf_blockwrite_digital_short_samples(hdl, buf.as_mut_ptr().cast::<c_short>()) }
+    }
+}
+
+impl RecordSample for [u8; 3] {
+    const OPERATION: &'static str = "blockwrite_digital_3byte_samples";
+
+    fn supports(filetype: Filetype) -> bool {
+        matches!(filetype, Filetype::BDF | Filetype::BDFPLUS)
+    }
+
+    unsafe fn blockwrite(hdl: c_int, buf: &[Self]) -> c_int {
+        // EDFlib only reads from the buffer
+        let buf = buf.as_ptr().cast_mut().cast::<c_void>();
+        unsafe { edf_blockwrite_digital_3byte_samples(hdl, buf) }
+    }
+}
+
+/// The samples of every signal for one datarecord, stored back to back in signal order as the
+/// `edf_blockwrite_*` functions expect them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordBuffer<T> {
+    samples: Vec<T>,
+    offsets: Vec<usize>,
+}
+
+impl<T: RecordSample> RecordBuffer<T> {
+    /// A zeroed datarecord with `samples_per_record[i]` samples for signal `i`.
+    pub fn new(samples_per_record: &[usize]) -> Self {
+        let mut offsets = Vec::with_capacity(samples_per_record.len() + 1);
+        offsets.push(0);
+        for samples in samples_per_record {
+            offsets.push(offsets[offsets.len() - 1] + samples);
+        }
+        let samples = vec![T::default(); offsets[offsets.len() - 1]];
+
+        RecordBuffer { samples, offsets }
+    }
+
+    pub fn number_of_signals(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn samples_per_record(&self, signal: usize) -> Option<usize> {
+        Some(self.offsets.get(signal + 1)? - self.offsets[signal])
+    }
+
+    pub fn signal(&self, signal: usize) -> &[T] {
+        &self.samples[self.offsets[signal]..self.offsets[signal + 1]]
+    }
+
+    pub fn signal_mut(&mut self, signal: usize) -> &mut [T] {
+        &mut self.samples[self.offsets[signal]..self.offsets[signal + 1]]
+    }
+
+    /// All samples of the datarecord in signal order.
+    pub fn as_slice(&self) -> &[T] {
+        &self.samples
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.samples
+    }
+}
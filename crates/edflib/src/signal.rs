@@ -0,0 +1,164 @@
+use std::io::SeekFrom;
+
+use crate::{ error::*, header::SignalInfo, reader::EdfReader };
+
+/// Number of samples fetched per FFI call when iterating sample by sample.
+const READ_AHEAD: usize = 4096;
+
+/// Sequential and random access to the samples of one signal, see [`EdfReader::signal`].
+///
+/// EDFlib keeps one position per signal and file, two `SignalReader`s for the same signal of
+/// the same [`EdfReader`] move each other's position.
+pub struct SignalReader<'a, 's> {
+    reader: &'a EdfReader<'s>,
+    signal: usize,
+    /// Samples read ahead by the `Iterator` implementation, `buffer[consumed..]` is unread.
+    buffer: Vec<f64>,
+    consumed: usize,
+}
+
+impl<'a, 's> SignalReader<'a, 's> {
+    pub(crate) fn new(reader: &'a EdfReader<'s>, signal: usize) -> Self {
+        SignalReader { reader, signal, buffer: Vec::new(), consumed: 0 }
+    }
+
+    pub fn info(&self) -> &'a SignalInfo {
+        &self.reader.signals()[self.signal]
+    }
+
+    fn context(&self, operation: &'static str) -> ErrorContext {
+        self.reader.context(operation).signal(self.signal as i32)
+    }
+
+    fn buffered(&self) -> &[f64] {
+        &self.buffer[self.consumed..]
+    }
+
+    /// Reads physical samples from the current position into `buf`, returns how many were read.
+    /// Fewer than `buf.len()` are only returned at the end of the signal.
+    pub fn read_into(&mut self, buf: &mut [f64]) -> Result<usize> {
+        let buffered = self.buffered().len().min(buf.len());
+        buf[..buffered].copy_from_slice(&self.buffered()[..buffered]);
+        self.consumed += buffered;
+
+        let mut read = buffered;
+        while read < buf.len() {
+            let n = self.read_physical(&mut buf[read..])?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+        Ok(read)
+    }
+
+    /// A single read from the backend, bypassing the read-ahead buffer.
+    fn read_physical(&self, buf: &mut [f64]) -> Result<usize> {
+        self.reader.read_physical(self.signal, buf)
+    }
+
+    /// The position of the next sample that will be read.
+    pub fn position(&self) -> Result<u64> {
+        Ok(self.reader.tell(self.signal)? - (self.buffered().len() as u64))
+    }
+
+    /// Moves the position, in samples. Seeking past the end stops at the end of the signal.
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => i64::try_from(offset).ok(),
+            SeekFrom::End(offset) => self.info().samples_in_file.checked_add(offset),
+            SeekFrom::Current(offset) => (SignalReader::position(self)? as i64).checked_add(offset),
+        };
+        let target = match target {
+            Some(target) if target >= 0 => target,
+            _ => {
+                return Err(
+                    EdfError::InvalidArgument(
+                        format!("can not seek to {:?}", pos),
+                        self.context("seek")
+                    )
+                );
+            }
+        };
+
+        self.buffer.clear();
+        self.consumed = 0;
+        self.reader.seek(self.signal, target)
+    }
+
+    pub fn rewind(&mut self) -> Result<()> {
+        self.seek(SeekFrom::Start(0)).map(|_| ())
+    }
+
+    /// Iterates over the rest of the signal in chunks of `size` samples, the last one may be
+    /// shorter.
+    pub fn chunks(&mut self, size: usize) -> Chunks<'_, 'a, 's> {
+        Chunks { signal: self, size: size.max(1) }
+    }
+}
+
+/// Gives back samples that were read ahead but not consumed, so the next reader of the signal
+/// continues where this one stopped.
+impl Drop for SignalReader<'_, '_> {
+    fn drop(&mut self) {
+        if self.buffered().is_empty() {
+            return;
+        }
+        let result = SignalReader::position(self).and_then(|position| {
+            self.reader.seek(self.signal, position as i64)
+        });
+        if let Err(error) = result {
+            log::error!("{}", error);
+        }
+    }
+}
+
+/// Sample by sample iteration, reading ahead in blocks. Iteration stops at the end of the
+/// signal and on read errors.
+impl Iterator for SignalReader<'_, '_> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        if self.buffered().is_empty() {
+            self.buffer.resize(READ_AHEAD, 0.0);
+            let mut buffer = std::mem::take(&mut self.buffer);
+            let read = self.read_physical(&mut buffer);
+            self.buffer = buffer;
+            self.consumed = 0;
+
+            match read {
+                Ok(n) => self.buffer.truncate(n),
+                Err(error) => {
+                    log::error!("{}", error);
+                    self.buffer.clear();
+                }
+            }
+        }
+
+        let sample = self.buffered().first().copied()?;
+        self.consumed += 1;
+        Some(sample)
+    }
+}
+
+/// Iterator over chunks of a signal, see [`SignalReader::chunks`].
+pub struct Chunks<'c, 'a, 's> {
+    signal: &'c mut SignalReader<'a, 's>,
+    size: usize,
+}
+
+impl Iterator for Chunks<'_, '_, '_> {
+    type Item = Result<Vec<f64>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = vec![0.0; self.size];
+        match self.signal.read_into(&mut chunk) {
+            Ok(0) => None,
+            Ok(n) => {
+                chunk.truncate(n);
+                Some(Ok(chunk))
+            }
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+
+use crate::{ error::*, Edf };
+
+/// Buffers samples that arrive per signal in chunks of any size and writes them to an [`Edf`]
+/// as complete datarecords, in the signal order EDFlib requires.
+///
+/// Samples are kept in memory until every signal has enough for a datarecord, so a signal that
+/// stops receiving samples makes the others grow without bound.
+///
+/// Once a datarecord fails to write, EDFlib may expect another signal than the first one next,
+/// so the writer stops and every later call fails as well.
+pub struct StreamingWriter {
+    edf: Edf,
+    samples_per_record: Vec<usize>,
+    buffers: Vec<VecDeque<f64>>,
+    record: Vec<f64>,
+    failed: bool,
+}
+
+impl StreamingWriter {
+    /// Wraps a writer whose signals are already configured, e.g. by
+    /// [`EdfWriterBuilder`](crate::EdfWriterBuilder).
+    pub fn new(edf: Edf) -> Self {
+        let samples_per_record: Vec<usize> = edf
+            .samples_per_record()
+            .into_iter()
+            .map(|samples| samples.max(0) as usize)
+            .collect();
+        let buffers = samples_per_record
+            .iter()
+            .map(|&samples| VecDeque::with_capacity(samples))
+            .collect();
+
+        StreamingWriter { edf, samples_per_record, buffers, record: Vec::new(), failed: false }
+    }
+
+    /// The underlying writer, e.g. to write annotations.
+    pub fn edf(&self) -> &Edf {
+        &self.edf
+    }
+
+    /// Appends `samples` to `signal` and writes every datarecord that is complete afterwards.
+    pub fn push(&mut self, signal: usize, samples: &[f64]) -> Result<()> {
+        let context = || self.edf.context("push").signal(signal as i32);
+        match self.samples_per_record.get(signal) {
+            None => {
+                let message = format!("signal {} does not exist", signal);
+                return Err(EdfError::InvalidArgument(message, context()));
+            }
+            Some(0) => {
+                return Err(EdfError::NoSamplesInRecord(context()));
+            }
+            Some(_) => {}
+        }
+        self.check_failed("push")?;
+        self.buffers[signal].extend(samples);
+
+        while self.record_complete() {
+            self.write_record()?;
+        }
+        Ok(())
+    }
+
+    /// Number of samples of `signal` waiting for the rest of their datarecord.
+    pub fn pending(&self, signal: usize) -> usize {
+        self.buffers.get(signal).map_or(0, VecDeque::len)
+    }
+
+    /// Writes the remaining samples, padding the last datarecord with zeros, and closes the file.
+    /// After a failed datarecord only the complete datarecords before it are kept.
+    pub fn finish(mut self) -> Result<()> {
+        self.flush()?;
+        self.edf.close()
+    }
+
+    fn record_complete(&self) -> bool {
+        self.buffers
+            .iter()
+            .zip(&self.samples_per_record)
+            .all(|(buffer, &samples)| buffer.len() >= samples)
+    }
+
+    fn check_failed(&self, operation: &'static str) -> Result<()> {
+        if self.failed {
+            return Err(
+                EdfError::InvalidArgument(
+                    "an earlier datarecord failed to write".to_string(),
+                    self.edf.context(operation)
+                )
+            );
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.check_failed("finish")?;
+        while self.buffers.iter().any(|buffer| !buffer.is_empty()) {
+            self.write_record()?;
+        }
+        Ok(())
+    }
+
+    /// Writes one datarecord, padding signals that do not have enough samples.
+    fn write_record(&mut self) -> Result<()> {
+        for (buffer, &samples) in self.buffers.iter_mut().zip(&self.samples_per_record) {
+            let available = buffer.len().min(samples);
+            self.record.clear();
+            self.record.extend(buffer.drain(..available));
+            self.record.resize(samples, 0.0);
+            if let Err(error) = self.edf.write_samples(&self.record) {
+                self.failed = true;
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StreamingWriter {
+    fn drop(&mut self) {
+        if self.failed {
+            return;
+        }
+        if let Err(error) = self.flush() {
+            log::error!("{}", error);
+        }
+    }
+}
@@ -1,12 +1,170 @@
-use std::{ ffi::{ CStr, CString }, os::raw::c_char };
+use std::{ ffi::CString, os::raw::{ c_char, c_int }, path::Path, time::Duration };
 
-pub fn str_to_char(input: &str) -> *const c_char {
-    CString::new(input).unwrap().into_raw()
+use crate::{ error::*, Filetype };
+
+/// Maximum number of signals in a file, `EDFLIB_MAXSIGNALS`.
+pub const MAX_SIGNALS: usize = 4096;
+/// Maximum length of a signal label in the header.
+pub const MAX_LABEL_LEN: usize = 16;
+/// Maximum length of a physical dimension (unit) in the header.
+pub const MAX_UNIT_LEN: usize = 8;
+/// Maximum length of the prefilter and transducer fields in the header.
+pub const MAX_SIGNAL_FIELD_LEN: usize = 80;
+/// Maximum length of a written annotation text in bytes, `EDFLIB_WRITE_MAX_ANNOTATION_LEN`.
+pub const MAX_WRITE_ANNOTATION_LEN: usize = 40;
+
+/// Encodes `input` as a null-terminated Latin-1 string, which is what the header setters and
+/// `edfwrite_annotation_latin1*` expect.
+///
+/// The returned `CString` owns the buffer, keep it alive for the duration of the FFI call.
+pub fn to_latin1(input: &str, context: impl FnOnce() -> ErrorContext) -> Result<CString> {
+    match input.chars().map(|c| u8::try_from(u32::from(c)).ok()).collect() {
+        Some(bytes) => to_cstring(bytes, context),
+        None => Err(EdfError::NotLatin1(context())),
+    }
+}
+
+/// Encodes `input` as a null-terminated UTF-8 string.
+pub fn to_utf8(input: &str, context: impl FnOnce() -> ErrorContext) -> Result<CString> {
+    to_cstring(input.as_bytes().to_vec(), context)
+}
+
+/// Encodes a path for `fopen`, which on all supported platforms takes UTF-8.
+pub fn path_to_cstring(path: &Path, context: impl FnOnce() -> ErrorContext) -> Result<CString> {
+    path.to_str()
+        .and_then(|path| CString::new(path).ok())
+        .ok_or_else(|| EdfError::InvalidPath(context()))
+}
+
+/// Cuts an annotation text to the bytes EDFlib writes, at a character boundary so no UTF-8
+/// sequence is split.
+pub fn truncate_annotation(text: &str) -> &str {
+    let mut end = text.len().min(MAX_WRITE_ANNOTATION_LEN);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn to_cstring(bytes: Vec<u8>, context: impl FnOnce() -> ErrorContext) -> Result<CString> {
+    CString::new(bytes).map_err(|_| EdfError::InteriorNul(context()))
+}
+
+/// Checks that a header field can be represented: Latin-1 without NUL and at most `max` long.
+pub fn check_field(
+    value: &str,
+    name: &str,
+    max: usize,
+    context: impl Fn() -> ErrorContext
+) -> Result<()> {
+    let value = to_latin1(value, &context)?;
+    if value.as_bytes().len() > max {
+        return Err(
+            EdfError::InvalidArgument(
+                format!("{} is longer than {} characters", name, max),
+                context()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Checks that an EDF+ identification subfield only contains printable US-ASCII and is at most
+/// `max` long.
+pub fn check_ascii_field(
+    value: &str,
+    name: &str,
+    max: usize,
+    context: impl Fn() -> ErrorContext
+) -> Result<()> {
+    if !value.bytes().all(|c| (b' '..=b'~').contains(&c)) {
+        return Err(
+            EdfError::InvalidArgument(
+                format!("{} may only contain printable ASCII characters", name),
+                context()
+            )
+        );
+    }
+    if value.len() > max {
+        return Err(
+            EdfError::InvalidArgument(
+                format!("{} is longer than {} characters", name, max),
+                context()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Checks that a physical minimum or maximum fits the 8 character header field.
+pub fn check_physical(value: f64, context: impl Fn() -> ErrorContext) -> Result<()> {
+    if !value.is_finite() || !(-9_999_999.0..=99_999_999.0).contains(&value) {
+        return Err(
+            EdfError::InvalidArgument(
+                format!("physical value {} does not fit in the header", value),
+                context()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Checks a digital minimum or maximum against the sample width of `filetype`.
+pub fn check_digital(
+    filetype: Filetype,
+    value: i32,
+    context: impl Fn() -> ErrorContext
+) -> Result<()> {
+    let (limit_min, limit_max) = filetype.digital_limits();
+    if value < limit_min || value > limit_max {
+        return Err(
+            EdfError::InvalidArgument(
+                format!(
+                    "digital value {} exceeds {}..{} of the filetype",
+                    value,
+                    limit_min,
+                    limit_max
+                ),
+                context()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// A datarecord duration in the units of the EDFlib function that accepts it.
+pub enum DatarecordUnits {
+    /// For `edf_set_datarecord_duration`: 1 ms to 60 s.
+    TenMicros(c_int),
+    /// For `edf_set_micro_datarecord_duration`: 1 us to 9999 us.
+    Micros(c_int),
+}
+
+pub fn datarecord_duration_units(duration: Duration) -> Option<DatarecordUnits> {
+    if !duration.subsec_nanos().is_multiple_of(1000) {
+        return None;
+    }
+    let micros = duration.as_micros();
+    if micros.is_multiple_of(10) && (1_000..=60_000_000).contains(&micros) {
+        Some(DatarecordUnits::TenMicros((micros / 10) as c_int))
+    } else if (1..=9_999).contains(&micros) {
+        Some(DatarecordUnits::Micros(micros as c_int))
+    } else {
+        None
+    }
+}
+
+/// Reads a fixed-size, null-terminated C string field into an owned `String`.
+pub fn chars_to_string(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).trim_end().to_string()
 }
 
-#[allow(unused)]
-pub fn char_to_str(ptr: *mut i8) -> String {
-    let cstr = unsafe { CStr::from_ptr(ptr) };
-    let result = cstr.to_str().unwrap().to_owned().to_string();
-    result
+/// Converts a time in units of 100 nanoseconds (`EDFLIB_TIME_DIMENSION` per second).
+pub fn ticks_to_duration(ticks: i64) -> Duration {
+    Duration::from_nanos((ticks.max(0) as u64) * 100)
 }
@@ -0,0 +1,76 @@
+use std::{ io::SeekFrom, time::Duration };
+
+use crate::{ error::*, reader::EdfReader };
+
+/// Samples of several signals between two points in time, see [`EdfReader::read_window`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Window {
+    /// The signal indexes, in the order they were requested.
+    pub signals: Vec<usize>,
+    /// The time of the first sample of each signal, relative to the start of the recording.
+    /// Signals with different sample rates can start at slightly different times.
+    pub start: Vec<Duration>,
+    /// Physical samples, one row per signal.
+    pub samples: Vec<Vec<f64>>,
+}
+
+/// Index of the first sample at or after `time` for a signal with `samples_in_datarecord`
+/// samples per `datarecord` of duration.
+fn sample_index(time: Duration, samples_in_datarecord: i32, datarecord: Duration) -> u128 {
+    let numerator = time.as_nanos() * (samples_in_datarecord as u128);
+    numerator.div_ceil(datarecord.as_nanos())
+}
+
+fn sample_time(index: u128, samples_in_datarecord: i32, datarecord: Duration) -> Duration {
+    let nanos = (index * datarecord.as_nanos()) / (samples_in_datarecord as u128);
+    Duration::from_nanos(nanos as u64)
+}
+
+impl EdfReader<'_> {
+    /// Reads the samples of `signals` in `start..end`, relative to the start of the recording.
+    /// The window is cut short at the end of the file.
+    pub fn read_window(&self, start: Duration, end: Duration, signals: &[usize]) -> Result<Window> {
+        let context = || self.context("read_window");
+        if start > end {
+            return Err(
+                EdfError::InvalidArgument(
+                    format!("window start {:?} is after its end {:?}", start, end),
+                    context()
+                )
+            );
+        }
+        let datarecord = self.header().datarecord_duration;
+        if datarecord.is_zero() {
+            return Err(
+                EdfError::InvalidArgument("file has no datarecord duration".to_string(), context())
+            );
+        }
+
+        let mut window = Window {
+            signals: signals.to_vec(),
+            start: Vec::with_capacity(signals.len()),
+            samples: Vec::with_capacity(signals.len()),
+        };
+        for &signal in signals {
+            let mut reader = self.signal(signal)?;
+            let info = reader.info();
+            let per_record = info.samples_in_datarecord;
+            let in_file = info.samples_in_file.max(0) as u128;
+
+            let first = sample_index(start, per_record, datarecord).min(in_file);
+            let last = sample_index(end, per_record, datarecord).min(in_file);
+
+            let mut samples = vec![0.0; (last - first) as usize];
+            if !samples.is_empty() {
+                reader.seek(SeekFrom::Start(first as u64))?;
+                let read = reader.read_into(&mut samples)?;
+                samples.truncate(read);
+            }
+
+            window.start.push(sample_time(first, per_record, datarecord));
+            window.samples.push(samples);
+        }
+        Ok(window)
+    }
+}
@@ -0,0 +1,105 @@
+//! Arrays written with `write_array` read back as arrays.
+
+#![cfg(all(feature = "ndarray", feature = "ffi"))]
+
+mod common;
+
+use edflib::*;
+use ndarray::Array2;
+
+use common::*;
+
+const DATARECORDS: usize = 3;
+
+fn signals(rates: &[f64]) -> Vec<SignalSpec> {
+    rates
+        .iter()
+        .map(|&rate| {
+            SignalSpec::new("EEG".into(), "uV".into(), rate, (-200.0, 200.0), (-32_768, 32_767))
+        })
+        .collect()
+}
+
+fn create(path: &TempPath, rates: &[f64]) -> Edf {
+    EdfWriterBuilder::new(path.to_path_buf())
+        .signals(signals(rates))
+        .start(start())
+        .build()
+        .unwrap()
+}
+
+fn rows(array: &Array2<f64>) -> Vec<Vec<f64>> {
+    array
+        .rows()
+        .into_iter()
+        .map(|row| row.to_vec())
+        .collect()
+}
+
+/// Moves every signal away from its first sample.
+fn read_some(reader: &EdfReader) {
+    for signal in 0..reader.number_of_signals() {
+        let mut buf = [0.0; 3];
+        reader.signal(signal).unwrap().read_into(&mut buf).unwrap();
+    }
+}
+
+#[test]
+fn equal_rates() {
+    let rates = [50.0; 3];
+    let path = TempPath::new("array-equal.edf");
+    let edf = create(&path, &rates);
+    let written = Array2::from_shape_fn((3, 50 * DATARECORDS), |(signal, sample)| {
+        (((signal * 31 + sample * 7) % 500) as f64) - 250.0
+    });
+    edf.write_array(written.view()).unwrap();
+    let error = edf.write_array(written.slice(ndarray::s![.., ..49])).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    let error = edf.write_array(written.slice(ndarray::s![..2, ..])).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    edf.finish().unwrap();
+
+    let reader = EdfReader::open(&path).unwrap();
+    assert_eq!(reader.header().datarecords_in_file, DATARECORDS as i64);
+    read_some(&reader);
+    let array = reader.read_all_array().unwrap();
+    assert_eq!(array.dim(), (3, 50 * DATARECORDS));
+    assert_samples(&rows(&array), &rows(&written), &signals(&rates));
+
+    // both read every signal from its first sample again
+    read_some(&reader);
+    let arrays = reader.read_all_arrays().unwrap();
+    let arrays: Vec<Vec<f64>> = arrays.iter().map(|array| array.to_vec()).collect();
+    assert_eq!(arrays, rows(&array));
+}
+
+#[test]
+fn mixed_rates() {
+    let rates = [50.0, 10.0];
+    let path = TempPath::new("array-mixed.edf");
+    let edf = create(&path, &rates);
+    let error = edf.write_array(Array2::zeros((2, 50)).view()).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+
+    let mut written = vec![Vec::new(); rates.len()];
+    let mut record = edf.record_buffer::<f64>();
+    for datarecord in 0..DATARECORDS {
+        for (signal, &rate) in rates.iter().enumerate() {
+            let samples = samples(signal, datarecord, rate as usize);
+            record.signal_mut(signal).copy_from_slice(&samples);
+            written[signal].extend(samples);
+        }
+        edf.write_record(&record).unwrap();
+    }
+    edf.finish().unwrap();
+
+    let reader = EdfReader::open(&path).unwrap();
+    let error = reader.read_all_array().unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    read_some(&reader);
+    let arrays = reader.read_all_arrays().unwrap();
+    let arrays: Vec<Vec<f64>> = arrays.iter().map(|array| array.to_vec()).collect();
+    assert_eq!(arrays[0].len(), 50 * DATARECORDS);
+    assert_eq!(arrays[1].len(), 10 * DATARECORDS);
+    assert_samples(&arrays, &written, &signals(&rates));
+}
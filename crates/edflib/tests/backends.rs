@@ -0,0 +1,311 @@
+//! Files written by EDFlib read the same through the C library and the native backend.
+//!
+//! EDFlib only writes EDF+ and BDF+, plain EDF and BDF files are put together byte by byte.
+
+#![cfg(all(feature = "ffi", feature = "pure-rust"))]
+
+mod common;
+
+use std::{ ffi::CString, fs, io::{ Read, Seek }, os::raw::c_int, path::Path };
+
+use edflib::*;
+use edflib_sys::*;
+
+use common::*;
+
+const RATES: [c_int; 3] = [200, 50, 7];
+const DATARECORDS: usize = 6;
+
+/// Writes the test signals with EDFlib itself, with or without annotations.
+fn write_with_edflib(path: &Path, filetype: u32, annotation_signals: Option<c_int>) {
+    let path_c = CString::new(path.to_str().unwrap()).unwrap();
+    let bdf = filetype == EDFLIB_FILETYPE_BDFPLUS;
+    let digital_max = if bdf { 8_388_607 } else { 32_767 };
+
+    unsafe {
+        let signals = RATES.len() as c_int;
+        let hdl = edfopen_file_writeonly(path_c.as_ptr(), filetype as c_int, signals);
+        assert!(hdl >= 0, "edfopen_file_writeonly returned {}", hdl);
+
+        for (signal, &rate) in RATES.iter().enumerate() {
+            let signal = signal as c_int;
+            assert_eq!(edf_set_samplefrequency(hdl, signal, rate), 0);
+            assert_eq!(edf_set_physical_maximum(hdl, signal, 100.0 + (signal as f64) * 0.3), 0);
+            assert_eq!(edf_set_physical_minimum(hdl, signal, -100.0), 0);
+            assert_eq!(edf_set_digital_maximum(hdl, signal, digital_max), 0);
+            assert_eq!(edf_set_digital_minimum(hdl, signal, -digital_max - 1), 0);
+            let label = CString::new(format!("Sig {}", signal)).unwrap();
+            assert_eq!(edf_set_label(hdl, signal, label.as_ptr()), 0);
+            assert_eq!(edf_set_physical_dimension(hdl, signal, c"uV".as_ptr()), 0);
+            assert_eq!(edf_set_prefilter(hdl, signal, c"HP:0.1Hz".as_ptr()), 0);
+        }
+        assert_eq!(edf_set_startdatetime(hdl, 2024, 5, 17, 13, 45, 7), 0);
+        assert_eq!(edf_set_patientname(hdl, c"Jane Doe".as_ptr()), 0);
+        assert_eq!(edf_set_patientcode(hdl, c"P-17".as_ptr()), 0);
+        assert_eq!(edf_set_equipment(hdl, c"amp 3".as_ptr()), 0);
+        if let Some(annotation_signals) = annotation_signals {
+            assert_eq!(edf_set_number_of_annotation_signals(hdl, annotation_signals), 0);
+            assert_eq!(edf_set_annot_chan_idx_pos(hdl, EDF_ANNOT_IDX_POS_START as c_int), 0);
+        }
+
+        for record in 0..DATARECORDS {
+            for (signal, &rate) in RATES.iter().enumerate() {
+                let mut buf = samples(signal, record, rate as usize);
+                assert_eq!(edfwrite_physical_samples(hdl, buf.as_mut_ptr()), 0);
+            }
+        }
+
+        if annotation_signals.is_some() {
+            let annotations: [(i64, i64, &std::ffi::CStr); 4] = [
+                (0, -1, c"start"),
+                (250_000, 0, c"zero duration"),
+                (1_500_000, 2_000_250, c"stimulus \xce\xa9"),
+                (5_999_999, -1, c"Recording ends"),
+            ];
+            for (onset, duration, text) in annotations {
+                let result = edfwrite_annotation_utf8_hr(hdl, onset, duration, text.as_ptr());
+                assert_eq!(result, 0);
+            }
+        }
+
+        assert_eq!(edfclose_file(hdl), 0);
+    }
+}
+
+fn assert_same_through_both_backends(path: &Path) {
+    let c = EdfReader::open(path).unwrap();
+    let native = EdfReader::open_native(path, ReadAnnotations::All).unwrap();
+
+    assert_eq!(c.header(), native.header());
+    assert_eq!(read_signals(&c), read_signals(&native));
+    assert_eq!(read_annotations(&c), read_annotations(&native));
+}
+
+/// The header of a plain EDF or BDF file with `samples` per datarecord for every signal.
+fn raw_header(bdf: bool, samples: &[usize], datarecords: usize) -> Vec<u8> {
+    let field = |value: &str, len: usize| format!("{:<len$}", value, len = len);
+    let signals = samples.len();
+    let filetype = if bdf { Filetype::BDF } else { Filetype::EDF };
+    let (digital_min, digital_max) = filetype.digital_limits();
+
+    let mut header = String::new();
+    header += &field("X X X X", 80);
+    header += &field("Startdate 17-MAY-2024 X X X", 80);
+    header += "17.05.2413.45.07";
+    header += &field(&((signals + 1) * 256).to_string(), 8);
+    header += &field("", 44);
+    header += &field(&datarecords.to_string(), 8);
+    header += &field("1", 8);
+    header += &field(&signals.to_string(), 4);
+    let column = |value: &dyn Fn(usize) -> String, len: usize| {
+        (0..signals).map(|signal| field(&value(signal), len)).collect::<String>()
+    };
+    header += &column(&|signal| format!("Sig {}", signal), 16);
+    header += &column(&|_| String::new(), 80);
+    header += &column(&|_| "uV".to_string(), 8);
+    header += &column(&|_| "-100".to_string(), 8);
+    header += &column(&|signal| format!("{}", 100.0 + (signal as f64) * 0.3), 8);
+    header += &column(&|_| digital_min.to_string(), 8);
+    header += &column(&|_| digital_max.to_string(), 8);
+    header += &column(&|_| "HP:0.1Hz".to_string(), 80);
+    header += &column(&|signal| samples[signal].to_string(), 8);
+    header += &column(&|_| String::new(), 32);
+
+    let version: &[u8] = if bdf { b"\xffBIOSEMI" } else { b"0       " };
+    [version, header.as_bytes()].concat()
+}
+
+/// A plain EDF or BDF file with the test signals.
+fn write_by_hand(path: &Path, bdf: bool) {
+    let samples: Vec<usize> = RATES.iter().map(|&rate| rate as usize).collect();
+    let mut bytes = raw_header(bdf, &samples, DATARECORDS);
+    let sample_size = if bdf { 3 } else { 2 };
+    for record in 0..DATARECORDS {
+        for (signal, &count) in samples.iter().enumerate() {
+            for i in 0..count {
+                let digital = ((signal * 7919 + record * 104_729 + i * 31) as i32) - 40_000;
+                bytes.extend_from_slice(&digital.to_le_bytes()[..sample_size]);
+            }
+        }
+    }
+    fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn edf() {
+    let path = TempPath::new("backends.edf");
+    write_by_hand(&path, false);
+    assert_same_through_both_backends(&path);
+}
+
+#[test]
+fn bdf() {
+    let path = TempPath::new("backends.bdf");
+    write_by_hand(&path, true);
+    assert_same_through_both_backends(&path);
+}
+
+#[test]
+fn edfplus_without_annotations() {
+    let path = TempPath::new("backends-plain-plus.edf");
+    write_with_edflib(&path, EDFLIB_FILETYPE_EDFPLUS, None);
+    assert_same_through_both_backends(&path);
+}
+
+#[test]
+fn edfplus_with_annotations() {
+    let path = TempPath::new("backends-annotations.edf");
+    write_with_edflib(&path, EDFLIB_FILETYPE_EDFPLUS, Some(1));
+    assert_same_through_both_backends(&path);
+
+    let native = EdfReader::open_native(&path, ReadAnnotations::All).unwrap();
+    assert_eq!(native.header().annotations_in_file, 4);
+}
+
+#[test]
+fn bdfplus_without_annotations() {
+    let path = TempPath::new("backends-plain-plus.bdf");
+    write_with_edflib(&path, EDFLIB_FILETYPE_BDFPLUS, None);
+    assert_same_through_both_backends(&path);
+}
+
+#[test]
+fn bdfplus_with_annotations() {
+    let path = TempPath::new("backends-annotations.bdf");
+    write_with_edflib(&path, EDFLIB_FILETYPE_BDFPLUS, Some(2));
+    assert_same_through_both_backends(&path);
+}
+
+#[test]
+fn until_recording_ends() {
+    let path = TempPath::new("backends-ends.edf");
+    write_with_edflib(&path, EDFLIB_FILETYPE_EDFPLUS, Some(1));
+
+    let c = EdfReader::open_with(&path, ReadAnnotations::UntilRecordingEnds).unwrap();
+    let native = EdfReader::open_native(&path, ReadAnnotations::UntilRecordingEnds).unwrap();
+    assert_eq!(read_annotations(&c), read_annotations(&native));
+}
+
+/// Both backends fail with the same error.
+fn assert_same_error(path: &Path) {
+    let c = EdfReader::open(path).err().expect("EDFlib opened the file");
+    let native = EdfReader::open_native(path, ReadAnnotations::All)
+        .err()
+        .expect("the native backend opened the file");
+    assert_eq!(c.code(), native.code(), "EDFlib: {}, native: {}", c, native);
+}
+
+#[test]
+fn truncated_files() {
+    let path = TempPath::new("backends-complete.edf");
+    write_with_edflib(&path, EDFLIB_FILETYPE_EDFPLUS, Some(1));
+    let bytes = fs::read(&path).unwrap();
+
+    let truncated = TempPath::new("backends-truncated.edf");
+    // inside the general header, inside the signal headers and inside the last datarecord
+    for len in [100, 256 + 100, bytes.len() - 1] {
+        fs::write(&truncated, &bytes[..len]).unwrap();
+        assert_same_error(&truncated);
+    }
+
+    // EDFlib also rejects bytes past the last datarecord
+    fs::write(&truncated, [bytes.as_slice(), &[0]].concat()).unwrap();
+    assert_same_error(&truncated);
+}
+
+#[test]
+fn discontinuous_files() {
+    for (name, filetype) in [
+        ("backends-discontinuous.edf", EDFLIB_FILETYPE_EDFPLUS),
+        ("backends-discontinuous.bdf", EDFLIB_FILETYPE_BDFPLUS),
+    ] {
+        let path = TempPath::new(name);
+        write_with_edflib(&path, filetype, Some(1));
+        let mut bytes = fs::read(&path).unwrap();
+        // "EDF+C" or "BDF+C" in the reserved field
+        bytes[196] = b'D';
+        fs::write(&path, &bytes).unwrap();
+
+        assert_same_error(&path);
+        let error = EdfReader::open_native(&path, ReadAnnotations::All).err().unwrap();
+        assert!(matches!(error, EdfError::FileIsDiscontinuous(_)), "{}", error);
+    }
+}
+
+#[test]
+fn without_datarecords() {
+    let path = TempPath::new("backends-empty.edf");
+    fs::write(&path, raw_header(false, &[200, 50], 0)).unwrap();
+    assert_same_error(&path);
+
+    // EDFlib writes files without datarecords, but does not read them
+    let path = TempPath::new("backends-empty-plus.edf");
+    let signal = SignalSpec::new("Sig 0".into(), "uV".into(), 200.0, (-100.0, 100.0), (-1, 1));
+    EdfWriterBuilder::new(path.to_path_buf()).signal(signal).build().unwrap().finish().unwrap();
+    assert_same_error(&path);
+    let error = EdfReader::open_native(&path, ReadAnnotations::All).err().unwrap();
+    assert!(matches!(error, EdfError::FileContainsFormatErrors(_)), "{}", error);
+}
+
+#[test]
+fn oversized_datarecords() {
+    let path = TempPath::new("backends-oversized.bdf");
+    // the end of the last datarecord does not fit in 64 bits
+    fs::write(&path, raw_header(true, &[99_999_999; 4096], 99_999_999)).unwrap();
+    assert_same_error(&path);
+
+    let error = EdfReader::open_native(&path, ReadAnnotations::All).err().unwrap();
+    assert!(matches!(error, EdfError::FileContainsFormatErrors(_)), "{}", error);
+}
+
+#[test]
+fn from_bytes() {
+    let path = TempPath::new("backends-bytes.edf");
+    write_with_edflib(&path, EDFLIB_FILETYPE_EDFPLUS, Some(1));
+    let bytes = fs::read(&path).unwrap();
+
+    let c = EdfReader::open(&path).unwrap();
+    let native = EdfReader::from_bytes(&bytes, ReadAnnotations::All).unwrap();
+    assert_eq!(c.header(), native.header());
+    assert_eq!(read_signals(&c), read_signals(&native));
+    assert_eq!(read_annotations(&c), read_annotations(&native));
+
+    // the reader borrows its source, which is free again once the reader is dropped
+    let mut file = fs::File::open(&path).unwrap();
+    let native = EdfReader::from_reader(&mut file, ReadAnnotations::All).unwrap();
+    assert_eq!(read_signals(&c), read_signals(&native));
+    drop(native);
+    file.rewind().unwrap();
+    let mut read = Vec::new();
+    file.read_to_end(&mut read).unwrap();
+    assert!(read == bytes, "the file differs");
+}
+
+#[test]
+fn overflowing_onset() {
+    let path = TempPath::new("backends-overflow.edf");
+    write_with_edflib(&path, EDFLIB_FILETYPE_EDFPLUS, Some(1));
+    let written = fs::read(&path).unwrap();
+    // an onset that does not fit in 64 bits of 100 ns
+    let crafted = "+922337203685.9999999\x14".as_bytes();
+    let replace = |tal: &[u8], by: &[u8]| {
+        let mut bytes = written.clone();
+        let position = bytes.windows(tal.len()).position(|window| window == tal).unwrap();
+        bytes[position..position + tal.len()].fill(0);
+        bytes[position..position + by.len()].copy_from_slice(by);
+        bytes
+    };
+
+    // the timekeeping TAL of the fifth datarecord
+    let bytes = replace(b"+4\x14\x14\x00", &[crafted, b"\x14"].concat());
+    fs::write(&path, &bytes).unwrap();
+    assert_same_error(&path);
+    let error = EdfReader::from_bytes(&bytes, ReadAnnotations::All).err().unwrap();
+    assert!(matches!(error, EdfError::FileContainsFormatErrors(_)), "{}", error);
+
+    // the onset of an annotation, EDFlib wraps around there
+    let tal = "+1.500000\x152.000250\x14stimulus \u{3a9}\x14".as_bytes();
+    let bytes = replace(tal, &[crafted, b"x\x14"].concat());
+    let error = EdfReader::from_bytes(&bytes, ReadAnnotations::All).err().unwrap();
+    assert!(matches!(error, EdfError::FileContainsFormatErrors(_)), "{}", error);
+}
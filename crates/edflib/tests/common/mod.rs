@@ -0,0 +1,94 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::{ env, fs, ops::Deref, path::{ Path, PathBuf }, process };
+
+use edflib::*;
+
+/// A file in the temporary directory, removed again when dropped.
+pub struct TempPath(PathBuf);
+
+impl TempPath {
+    /// `name` has to be unique within the test binary, the extension picks EDF or BDF.
+    pub fn new(name: &str) -> Self {
+        TempPath(env::temp_dir().join(format!("edflib-{}-{}", process::id(), name)))
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// A fixed start, so files written at different times compare equal.
+pub fn start() -> StartDateTime {
+    StartDateTime {
+        year: 2024,
+        month: 5,
+        day: 17,
+        hour: 13,
+        minute: 45,
+        second: 7,
+        subsecond: Default::default(),
+    }
+}
+
+/// Test samples for `signal`, a ramp that also leaves the physical range of the signal.
+pub fn samples(signal: usize, record: usize, count: usize) -> Vec<f64> {
+    (0..count)
+        .map(|i| ((signal * 37 + record * 11 + i) % 260) as f64 - 130.0)
+        .collect()
+}
+
+/// Every sample of every signal, read from the start.
+pub fn read_signals(reader: &EdfReader) -> Vec<Vec<f64>> {
+    (0..reader.number_of_signals())
+        .map(|signal| {
+            let mut signal = reader.signal(signal).unwrap();
+            signal.rewind().unwrap();
+            let mut samples = vec![0.0; signal.info().samples_in_file as usize];
+            let read = signal.read_into(&mut samples).unwrap();
+            samples.truncate(read);
+            samples
+        })
+        .collect()
+}
+
+pub fn read_annotations(reader: &EdfReader) -> Vec<Annotation> {
+    reader
+        .annotations()
+        .collect::<Result<_>>()
+        .unwrap()
+}
+
+/// Checks `read` against the `written` samples of `signals`: clamped to the physical range and
+/// within one step of the digital range.
+pub fn assert_samples(read: &[Vec<f64>], written: &[Vec<f64>], signals: &[SignalSpec]) {
+    assert_eq!(read.len(), written.len());
+    for ((read, written), spec) in read.iter().zip(written).zip(signals) {
+        let (minimum, maximum) = spec.physical_range;
+        let (digital_minimum, digital_maximum) = spec.digital_range;
+        let step = (maximum - minimum) / ((digital_maximum - digital_minimum) as f64);
+        assert_eq!(read.len(), written.len());
+        for (&read, &written) in read.iter().zip(written) {
+            let expected = written.clamp(minimum, maximum);
+            assert!((read - expected).abs() <= step, "read {} for {}", read, expected);
+        }
+    }
+}
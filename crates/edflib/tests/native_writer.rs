@@ -0,0 +1,247 @@
+//! The native writer writes the same bytes as EDFlib for the same settings.
+
+#![cfg(all(feature = "ffi", feature = "pure-rust"))]
+
+mod common;
+
+use std::{ fs, path::Path, time::Duration };
+
+use edflib::*;
+
+use common::*;
+
+/// An annotation to write: onset, duration and text.
+type Event = (Duration, Option<Duration>, &'static str);
+
+const EVENTS: [Event; 6] = [
+    (Duration::ZERO, None, "start"),
+    (Duration::from_millis(250), Some(Duration::ZERO), "zero duration"),
+    (Duration::from_micros(1_500_017), Some(Duration::from_micros(2_000_250)), "stimulus Ω"),
+    (Duration::from_secs(2), Some(Duration::from_secs(3)), "tab\tand\nnewline"),
+    (
+        Duration::from_millis(2_100),
+        None,
+        "a text that is longer than the forty bytes EDFlib keeps: ΩΩΩ",
+    ),
+    (Duration::from_micros(5_999_999), None, "Recording ends"),
+];
+
+fn signals() -> Vec<SignalSpec> {
+    vec![
+        SignalSpec::new(" EEG Fp1".into(), "uV".into(), 200.0, (-100.3, 100.3), (-32_768, 32_767))
+            .with_prefilter("HP:0.1Hz LP:75Hz ")
+            .with_transducer("AgAgCl cup electrodes"),
+        SignalSpec::new("Temp °C".into(), "°C".into(), 50.0, (-0.5, 41.25), (-2_000, 2_000)),
+        SignalSpec::new("Trigger".into(), "".into(), 10.0, (0.0, 1.0), (0, 1)),
+    ]
+}
+
+/// Writes `records` datarecords, then the first `partial` signals of one more, and `events`
+/// with EDFlib and with the native writer, and returns the bytes of both files.
+fn write_both(
+    name: &str,
+    builder: impl Fn(&Path) -> EdfWriterBuilder,
+    records: usize,
+    partial: usize,
+    events: &[Event]
+) -> (Vec<u8>, Vec<u8>) {
+    let c_path = TempPath::new(&format!("c-{}", name));
+    let native_path = TempPath::new(&format!("native-{}", name));
+
+    let edf = builder(&c_path).build().unwrap();
+    let samples_per_record = edf.samples_per_record();
+    let mut native = builder(&native_path).build_native().unwrap();
+    assert_eq!(
+        native.samples_per_record(),
+        samples_per_record.iter().map(|&samples| samples as usize).collect::<Vec<_>>()
+    );
+
+    let signals = samples_per_record.len();
+    for index in 0..records * signals + partial {
+        let (record, signal) = (index / signals, index % signals);
+        let samples = samples(signal, record, samples_per_record[signal] as usize);
+        edf.write_samples(&samples).unwrap();
+        native.write_samples(&samples).unwrap();
+    }
+    for &(onset, duration, text) in events {
+        edf.write_annotation(onset, duration, text).unwrap();
+        native.write_annotation(onset, duration, text).unwrap();
+    }
+    edf.finish().unwrap();
+    native.finish().unwrap();
+
+    (fs::read(&c_path).unwrap(), fs::read(&native_path).unwrap())
+}
+
+fn assert_same_bytes(name: &str, builder: impl Fn(&Path) -> EdfWriterBuilder) {
+    let (c, native) = write_both(name, builder, 6, 0, &EVENTS);
+    assert_eq!(c.len(), native.len());
+    let header_size = std::str::from_utf8(&c[184..192]).unwrap().trim().parse().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&c[..header_size]),
+        String::from_utf8_lossy(&native[..header_size])
+    );
+    assert!(c == native, "the datarecords differ");
+}
+
+fn builder(path: &Path) -> EdfWriterBuilder {
+    EdfWriterBuilder::new(path.to_path_buf()).signals(signals()).start(start())
+}
+
+#[test]
+fn edf_plus() {
+    assert_same_bytes("plus.edf", builder);
+}
+
+#[test]
+fn bdf_plus() {
+    assert_same_bytes("plus.bdf", |path| {
+        let mut signals = signals();
+        signals[0].digital_range = (-8_388_608, 8_388_607);
+        EdfWriterBuilder::new(path.to_path_buf()).signals(signals).start(start())
+    });
+}
+
+#[test]
+fn annotation_positions() {
+    for (name, position, annotation_signals) in [
+        ("start.edf", AnnotationPosition::Start, 1),
+        ("middle.edf", AnnotationPosition::Middle, 2),
+        ("end.bdf", AnnotationPosition::End, 3),
+    ] {
+        assert_same_bytes(name, |path| {
+            builder(path).annotation_position(position).annotation_signals(annotation_signals)
+        });
+    }
+}
+
+#[test]
+fn datarecord_duration_and_subsecond_start() {
+    assert_same_bytes("subsecond.edf", |path| {
+        let start = StartDateTime { subsecond: Duration::from_nanos(123_456_700), ..start() };
+        builder(path).start(start)
+    });
+    assert_same_bytes("duration.edf", |path| {
+        builder(path).datarecord_duration(Duration::from_millis(300))
+    });
+    assert_same_bytes("both.bdf", |path| {
+        let start = StartDateTime { subsecond: Duration::from_millis(500), ..start() };
+        builder(path).start(start).datarecord_duration(Duration::from_millis(1_100))
+    });
+}
+
+#[test]
+fn patient_and_recording() {
+    let patient = PatientInfo {
+        code: " MCH-0234567 ".to_string(),
+        sex: Some(Sex::Female),
+        birthdate: Some(Birthdate { year: 1951, month: 8, day: 2 }),
+        name: "Haagse Harry".to_string(),
+        // code, name, a space and additional fill the 62 characters left by the birthdate
+        additional: "0123456789".repeat(4)[..38].to_string(),
+    };
+    let recording = RecordingInfo {
+        admincode: "EMR 7".to_string(),
+        technician: "".to_string(),
+        equipment: "amp 3".to_string(),
+        // all subfields fill the 52 characters after the start date
+        additional: "0123456789".repeat(5)[..42].to_string(),
+    };
+    assert_same_bytes("identification.edf", |path| {
+        builder(path).patient(patient.clone()).recording(recording.clone())
+    });
+    assert_same_bytes("unknown.edf", |path| {
+        builder(path).patient(PatientInfo::default()).recording(RecordingInfo::default())
+    });
+
+    let (_, native) = write_both(
+        "identification-read.edf",
+        |path| builder(path).patient(patient.clone()).recording(recording.clone()),
+        1,
+        0,
+        &[]
+    );
+    let header = EdfReader::from_bytes(&native, ReadAnnotations::All).unwrap().header().clone();
+    assert_eq!(header.patient_additional, patient.additional);
+    assert_eq!(header.recording_additional, recording.additional);
+
+    // one more character would be cut off, both backends refuse it
+    let mut long_patient = patient.clone();
+    long_patient.additional.push('x');
+    let without_birthdate = PatientInfo {
+        birthdate: None,
+        additional: "0123456789".repeat(5)[..49].to_string(),
+        ..patient.clone()
+    };
+    let mut long_recording = recording.clone();
+    long_recording.additional.push('x');
+    let too_long: [&dyn Fn(&Path) -> EdfWriterBuilder; 3] = [
+        &|path| builder(path).patient(long_patient.clone()),
+        &|path| builder(path).patient(without_birthdate.clone()),
+        &|path| builder(path).recording(long_recording.clone()),
+    ];
+    let path = TempPath::new("too-long.edf");
+    for builder in too_long {
+        let error = builder(&path).build_native().err().unwrap();
+        assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+        let error = builder(&path).build().err().unwrap();
+        assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    }
+    let mut fits = without_birthdate;
+    fits.additional.pop();
+    assert!(builder(&path).patient(fits).build_native().is_ok());
+}
+
+#[test]
+fn incomplete_datarecord() {
+    let (c, native) = write_both("incomplete.edf", builder, 3, 2, &EVENTS[..3]);
+    assert!(c == native, "the files differ");
+    let (c, native) = write_both(
+        "incomplete-middle.edf",
+        |path| builder(path).annotation_position(AnnotationPosition::Middle),
+        2,
+        1,
+        &[]
+    );
+    assert!(c == native, "the files differ");
+}
+
+#[test]
+fn without_datarecords() {
+    let (c, native) = write_both("empty.edf", builder, 0, 0, &[]);
+    assert!(c == native, "the files differ");
+
+    // EDFlib writes datarecords with only the annotation signals, one per annotation
+    let (c, native) = write_both("annotations-only.edf", builder, 0, 0, &EVENTS[..3]);
+    assert!(c == native, "the files differ");
+    let (c, native) = write_both("annotations-over-samples.edf", builder, 0, 2, &EVENTS);
+    assert!(c == native, "the files differ");
+    let (c, native) = write_both(
+        "annotations-only-end.bdf",
+        |path| {
+            builder(path)
+                .annotation_position(AnnotationPosition::End)
+                .annotation_signals(2)
+                .datarecord_duration(Duration::from_millis(300))
+        },
+        0,
+        1,
+        &EVENTS
+    );
+    assert!(c == native, "the files differ");
+}
+
+#[test]
+fn more_annotations_than_datarecords() {
+    // EDFlib drops the annotations that do not fit
+    let (c, native) = write_both("dropped.edf", builder, 2, 0, &EVENTS);
+    assert!(c == native, "the files differ");
+    let (c, native) = write_both(
+        "dropped-middle.edf",
+        |path| builder(path).annotation_position(AnnotationPosition::Middle).annotation_signals(2),
+        1,
+        1,
+        &EVENTS
+    );
+    assert!(c == native, "the files differ");
+}
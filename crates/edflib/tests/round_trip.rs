@@ -0,0 +1,299 @@
+//! Files written with the native writer read back with what was written.
+
+#![cfg(feature = "pure-rust")]
+
+mod common;
+
+use std::{ io::{ Cursor, Seek, SeekFrom, Write }, time::Duration };
+
+use edflib::*;
+
+use common::*;
+
+const DATARECORDS: usize = 3;
+
+fn signals() -> Vec<SignalSpec> {
+    vec![
+        SignalSpec::new("EEG Fp1".into(), "uV".into(), 200.0, (-200.0, 200.0), (-32_768, 32_767)),
+        SignalSpec::new("Resp".into(), "mV".into(), 25.0, (-150.0, 150.0), (-2_048, 2_047))
+    ]
+}
+
+fn annotations() -> Vec<Annotation> {
+    vec![
+        Annotation { onset: Duration::ZERO, duration: None, text: "start".to_string() },
+        Annotation {
+            onset: Duration::from_micros(1_250_000),
+            duration: Some(Duration::from_millis(500)),
+            text: "stimulus".to_string(),
+        }
+    ]
+}
+
+/// Writes the test signals and annotations, returns the samples written per signal.
+fn write_native<W: Write + Seek>(writer: &mut EdfWriter<W>) -> Vec<Vec<f64>> {
+    let samples_per_record = writer.samples_per_record();
+    let mut written = vec![Vec::new(); samples_per_record.len()];
+    for record in 0..DATARECORDS {
+        for (signal, &count) in samples_per_record.iter().enumerate() {
+            let samples = samples(signal, record, count);
+            writer.write_samples(&samples).unwrap();
+            written[signal].extend(samples);
+        }
+    }
+    for annotation in annotations() {
+        writer.write_annotation(annotation.onset, annotation.duration, &annotation.text).unwrap();
+    }
+    written
+}
+
+fn builder() -> EdfWriterBuilder {
+    EdfWriterBuilder::default().filetype(Filetype::EDFPLUS).signals(signals()).start(start())
+}
+
+/// The test file in memory and the samples written per signal.
+fn file_in_memory() -> (Vec<u8>, Vec<Vec<f64>>) {
+    let mut writer = EdfWriter::to_writer(Cursor::new(Vec::new()), builder()).unwrap();
+    let written = write_native(&mut writer);
+    (writer.finish().unwrap().into_inner(), written)
+}
+
+#[test]
+fn finish_with_more_annotations_than_datarecords() {
+    let mut writer = EdfWriter::to_writer(Cursor::new(Vec::new()), builder()).unwrap();
+    for (signal, &count) in writer.samples_per_record().iter().enumerate() {
+        writer.write_samples(samples(signal, 0, count)).unwrap();
+    }
+    for annotation in annotations() {
+        writer.write_annotation(annotation.onset, annotation.duration, &annotation.text).unwrap();
+    }
+    // the annotation that does not fit is dropped, the sink comes back all the same
+    let bytes = writer.finish().unwrap().into_inner();
+
+    let reader = EdfReader::from_bytes(&bytes, ReadAnnotations::All).unwrap();
+    assert_eq!(reader.header().datarecords_in_file, 1);
+    assert_eq!(read_annotations(&reader), annotations()[..1]);
+}
+
+#[test]
+fn to_writer_after_other_bytes() {
+    let prefix = vec![0xa5; 512];
+    let mut sink = Cursor::new(prefix.clone());
+    sink.seek(SeekFrom::End(0)).unwrap();
+    let mut writer = EdfWriter::to_writer(sink, builder()).unwrap();
+    let written = write_native(&mut writer);
+    let bytes = writer.finish().unwrap().into_inner();
+
+    let mut alone = EdfWriter::to_writer(Cursor::new(Vec::new()), builder()).unwrap();
+    write_native(&mut alone);
+    let alone = alone.finish().unwrap().into_inner();
+
+    assert_eq!(bytes[..512], prefix[..]);
+    assert!(bytes[512..] == alone[..], "the file differs after other bytes");
+
+    let reader = EdfReader::from_bytes(&bytes[512..], ReadAnnotations::All).unwrap();
+    assert_eq!(reader.header().datarecords_in_file, DATARECORDS as i64);
+    assert_samples(&read_signals(&reader), &written, &signals());
+    assert_eq!(read_annotations(&reader), annotations());
+}
+
+#[test]
+fn signal_reader_seeks_and_iterates() {
+    let (bytes, written) = file_in_memory();
+    let reader = EdfReader::from_bytes(&bytes, ReadAnnotations::All).unwrap();
+    let all = read_signals(&reader);
+    assert_samples(&all, &written, &signals());
+
+    let mut signal = reader.signal(0).unwrap();
+    let len = all[0].len() as u64;
+    assert_eq!(signal.info().samples_in_file, len as i64);
+
+    let mut buf = [0.0; 50];
+    assert_eq!(signal.seek(SeekFrom::Start(230)).unwrap(), 230);
+    assert_eq!(signal.read_into(&mut buf).unwrap(), 50);
+    assert_eq!(buf[..], all[0][230..280]);
+    assert_eq!(signal.position().unwrap(), 280);
+
+    assert_eq!(signal.seek(SeekFrom::Current(-80)).unwrap(), 200);
+    assert_eq!(signal.read_into(&mut buf).unwrap(), 50);
+    assert_eq!(buf[..], all[0][200..250]);
+
+    // reads stop at the end of the signal, seeks past it stop there too
+    assert_eq!(signal.seek(SeekFrom::End(-20)).unwrap(), len - 20);
+    assert_eq!(signal.read_into(&mut buf).unwrap(), 20);
+    assert_eq!(buf[..20], all[0][all[0].len() - 20..]);
+    assert_eq!(signal.seek(SeekFrom::End(10)).unwrap(), len);
+    assert_eq!(signal.read_into(&mut buf).unwrap(), 0);
+    let error = signal.seek(SeekFrom::Current(-(len as i64) - 1)).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+
+    // the iterator reads ahead, the position still counts the samples it returned
+    signal.rewind().unwrap();
+    let first: Vec<f64> = signal.by_ref().take(10).collect();
+    assert_eq!(first[..], all[0][..10]);
+    assert_eq!(signal.position().unwrap(), 10);
+    assert_eq!(signal.read_into(&mut buf).unwrap(), 50);
+    assert_eq!(buf[..], all[0][10..60]);
+
+    let chunks: Vec<Vec<f64>> = signal.chunks(64).collect::<Result<_>>().unwrap();
+    assert_eq!(chunks.len(), (all[0].len() - 60).div_ceil(64));
+    assert!(chunks[..chunks.len() - 1].iter().all(|chunk| chunk.len() == 64));
+    assert_eq!(chunks.concat(), all[0][60..]);
+
+    // a dropped reader gives back what it read ahead
+    let mut signal = reader.signal(1).unwrap();
+    signal.rewind().unwrap();
+    assert_eq!(signal.next(), Some(all[1][0]));
+    drop(signal);
+    let rest: Vec<f64> = reader.signal(1).unwrap().collect();
+    assert_eq!(rest[..], all[1][1..]);
+}
+
+#[test]
+fn read_window_of_a_written_file() {
+    let (bytes, _) = file_in_memory();
+    let reader = EdfReader::from_bytes(&bytes, ReadAnnotations::All).unwrap();
+    let all = read_signals(&reader);
+
+    // the first sample of each signal at or after the start, up to the one before the end
+    let window = reader
+        .read_window(Duration::from_millis(1_230), Duration::from_millis(2_500), &[1, 0])
+        .unwrap();
+    assert_eq!(window.signals, [1, 0]);
+    assert_eq!(window.start, [Duration::from_millis(1_240), Duration::from_millis(1_230)]);
+    assert_eq!(window.samples[0][..], all[1][31..63]);
+    assert_eq!(window.samples[1][..], all[0][246..500]);
+
+    // cut short at the end of the file
+    let window = reader
+        .read_window(Duration::from_millis(2_900), Duration::from_secs(10), &[0, 1])
+        .unwrap();
+    assert_eq!(window.start, [Duration::from_millis(2_900), Duration::from_millis(2_920)]);
+    assert_eq!(window.samples[0][..], all[0][580..]);
+    assert_eq!(window.samples[1][..], all[1][73..]);
+
+    let window = reader.read_window(Duration::from_secs(5), Duration::from_secs(6), &[0]).unwrap();
+    assert_eq!(window.start, [Duration::from_secs(3)]);
+    assert!(window.samples[0].is_empty());
+
+    let error = reader
+        .read_window(Duration::from_secs(2), Duration::from_secs(1), &[0])
+        .unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    assert!(reader.read_window(Duration::ZERO, Duration::from_secs(1), &[2]).is_err());
+}
+
+/// Little endian samples as written for EDF (2 bytes) and BDF (3 bytes).
+#[cfg(feature = "mmap")]
+fn decode(bytes: &[u8], sample_size: usize) -> Vec<i32> {
+    bytes
+        .chunks_exact(sample_size)
+        .map(|sample| {
+            let mut digital = [0; 4];
+            digital[4 - sample_size..].copy_from_slice(sample);
+            i32::from_le_bytes(digital) >> (8 * (4 - sample_size))
+        })
+        .collect()
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn mapped_file_reads_like_the_reader() {
+    for (name, filetype, sample_size) in [
+        ("mapped.edf", Filetype::EDFPLUS, 2),
+        ("mapped.bdf", Filetype::BDFPLUS, 3),
+    ] {
+        let path = TempPath::new(name);
+        let mut writer = EdfWriterBuilder::new(path.to_path_buf())
+            .filetype(filetype)
+            .signals(signals())
+            .start(start())
+            .build_native()
+            .unwrap();
+        let written = write_native(&mut writer);
+        writer.finish().unwrap();
+
+        let reader = EdfReader::open_native(&path, ReadAnnotations::All).unwrap();
+        let all = read_signals(&reader);
+        assert_samples(&all, &written, &signals());
+        let mapped = unsafe { MappedEdf::open(&path, ReadAnnotations::All) }.unwrap();
+        assert_eq!(mapped.header(), reader.header());
+        assert_eq!(mapped.signals(), reader.signals());
+        assert_eq!(mapped.annotations().collect::<Result<Vec<_>>>().unwrap(), annotations());
+        assert_eq!(mapped.datarecords(), DATARECORDS);
+        assert!(mapped.datarecord(DATARECORDS).is_none());
+
+        // the datarecords follow the header without gaps
+        let bytes = std::fs::read(&path).unwrap();
+        let header_size = (mapped.number_of_signals() + 2) * 256;
+        let records: Vec<u8> = mapped
+            .iter_datarecords()
+            .flat_map(|record| record.bytes().iter().copied())
+            .collect();
+        assert!(records == bytes[header_size..], "the datarecords differ from the file");
+
+        for (signal, samples) in all.iter().enumerate() {
+            let mut physical = Vec::new();
+            let mut digital = Vec::new();
+            for (index, record) in mapped.iter_datarecords().enumerate() {
+                assert_eq!(record.index(), index);
+                let per_record = mapped.signals()[signal].samples_in_datarecord as usize;
+                let raw = record.signal_bytes(signal).unwrap();
+                assert_eq!(raw.len(), per_record * sample_size);
+
+                let mut buf = vec![0.0; per_record + 1];
+                assert_eq!(record.read_physical(signal, &mut buf).unwrap(), per_record);
+                physical.extend_from_slice(&buf[..per_record]);
+                let mut buf = vec![0; per_record];
+                assert_eq!(record.read_digital(signal, &mut buf).unwrap(), per_record);
+                assert_eq!(buf, decode(raw, sample_size));
+                digital.extend(buf);
+            }
+            assert_eq!(&physical, samples);
+
+            // across datarecords and cut short at the end of the signal
+            let start = samples.len() - samples.len() / 3 - 7;
+            let mut buf = vec![0.0; samples.len()];
+            let read = mapped.read_physical(signal, start as u64, &mut buf).unwrap();
+            assert_eq!(buf[..read], samples[start..]);
+            let mut buf = vec![0; samples.len()];
+            let read = mapped.read_digital(signal, start as u64, &mut buf).unwrap();
+            assert_eq!(buf[..read], digital[start..]);
+            assert_eq!(mapped.read_digital(signal, samples.len() as u64, &mut buf).unwrap(), 0);
+        }
+
+        let error = mapped.datarecord(0).unwrap().signal_bytes(2).unwrap_err();
+        assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+        let error = mapped.read_physical(2, 0, &mut [0.0; 4]).unwrap_err();
+        assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    }
+}
+
+#[test]
+fn picks_the_shortest_datarecord_above_a_second() {
+    for (rates, expected) in [
+        (vec![200.0, 25.0], Duration::from_secs(1)),
+        (vec![2.0 / 3.0], Duration::from_millis(1_500)),
+        (vec![0.4, 10.0], Duration::from_millis(2_500)),
+        (vec![2.0 / 3.0, 0.4], Duration::from_millis(7_500)),
+        (vec![2_500.5], Duration::from_secs(2)),
+    ] {
+        let signals = rates
+            .iter()
+            .map(|&rate| {
+                SignalSpec::new("EEG".into(), "uV".into(), rate, (-200.0, 200.0), (-2_048, 2_047))
+            })
+            .collect::<Vec<_>>();
+        let builder = EdfWriterBuilder::default()
+            .filetype(Filetype::EDFPLUS)
+            .signals(signals)
+            .start(start());
+        let mut writer = EdfWriter::to_writer(Cursor::new(Vec::new()), builder).unwrap();
+        for &samples in &writer.samples_per_record() {
+            writer.write_samples(vec![0.0; samples]).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+        let reader = EdfReader::from_bytes(&bytes, ReadAnnotations::All).unwrap();
+        assert_eq!(reader.header().datarecord_duration, expected, "{:?}", rates);
+    }
+}
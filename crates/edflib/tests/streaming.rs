@@ -0,0 +1,113 @@
+//! Chunks pushed to a `StreamingWriter` in any order read back as complete datarecords.
+
+#![cfg(feature = "ffi")]
+
+mod common;
+
+use std::fs;
+
+use edflib::*;
+
+use common::*;
+
+/// Samples per datarecord of every signal, the datarecord is one second long.
+const SAMPLES_PER_RECORD: [usize; 3] = [100, 20, 7];
+
+fn signals() -> Vec<SignalSpec> {
+    SAMPLES_PER_RECORD.iter()
+        .enumerate()
+        .map(|(signal, &rate)| {
+            let label = format!("signal {}", signal);
+            SignalSpec::new(label, "uV".into(), rate as f64, (-200.0, 200.0), (-2_048, 2_047))
+        })
+        .collect()
+}
+
+fn create(path: &TempPath) -> StreamingWriter {
+    let edf = EdfWriterBuilder::new(path.to_path_buf()).signals(signals()).start(start()).build();
+    StreamingWriter::new(edf.unwrap())
+}
+
+/// Pushes `seconds` of samples in chunks of changing sizes, the signals take turns in a
+/// different order every round. Returns the pushed samples per signal.
+fn push_chunks(writer: &mut StreamingWriter, seconds: f64) -> Vec<Vec<f64>> {
+    let totals: Vec<usize> = SAMPLES_PER_RECORD.iter()
+        .map(|&rate| ((rate as f64) * seconds).round() as usize)
+        .collect();
+    let mut pushed = vec![Vec::new(); totals.len()];
+    for round in 0.. {
+        if pushed.iter().zip(&totals).all(|(pushed, &total)| pushed.len() == total) {
+            break;
+        }
+        for turn in 0..totals.len() {
+            let signal = (turn * 2 + round) % totals.len();
+            let chunk = (round * 7 + signal * 3) % (SAMPLES_PER_RECORD[signal] * 2) + 1;
+            let chunk = chunk.min(totals[signal] - pushed[signal].len());
+            let samples = samples(signal, round, chunk);
+            writer.push(signal, &samples).unwrap();
+            pushed[signal].extend(samples);
+        }
+    }
+    pushed
+}
+
+/// The pushed samples with the last datarecord padded with zeros.
+fn padded(mut pushed: Vec<Vec<f64>>, datarecords: usize) -> Vec<Vec<f64>> {
+    for (samples, &per_record) in pushed.iter_mut().zip(&SAMPLES_PER_RECORD) {
+        samples.resize(datarecords * per_record, 0.0);
+    }
+    pushed
+}
+
+#[test]
+fn chunks_in_any_order() {
+    let path = TempPath::new("streaming-chunks.edf");
+    let mut writer = create(&path);
+    let pushed = push_chunks(&mut writer, 5.5);
+    // complete datarecords are written as soon as every signal has one
+    assert!((0..SAMPLES_PER_RECORD.len()).all(|signal| writer.pending(signal) < 2 * 100));
+    writer.finish().unwrap();
+
+    let reader = EdfReader::open(&path).unwrap();
+    assert_eq!(reader.header().datarecords_in_file, 6);
+    assert_samples(&read_signals(&reader), &padded(pushed, 6), &signals());
+}
+
+#[test]
+fn drop_pads_the_last_datarecord() {
+    let path = TempPath::new("streaming-drop.edf");
+    let mut writer = create(&path);
+    let pushed = push_chunks(&mut writer, 2.0);
+    writer.push(1, &[50.0]).unwrap();
+    assert_eq!(writer.pending(1), 1);
+    assert_eq!(writer.pending(0), 0);
+    drop(writer);
+
+    let mut expected = pushed;
+    expected[1].push(50.0);
+    let reader = EdfReader::open(&path).unwrap();
+    assert_eq!(reader.header().datarecords_in_file, 3);
+    assert_samples(&read_signals(&reader), &padded(expected, 3), &signals());
+}
+
+#[test]
+fn stops_after_a_failed_datarecord() {
+    let path = TempPath::new("streaming-failed.edf");
+    let mut writer = create(&path);
+    push_chunks(&mut writer, 1.0);
+
+    // EDFlib now expects the second signal, the next datarecord starts with the first
+    writer.edf().write_samples(samples(0, 0, 100)).unwrap();
+    for (signal, &per_record) in SAMPLES_PER_RECORD.iter().enumerate().rev() {
+        let result = writer.push(signal, &samples(signal, 1, per_record));
+        assert_eq!(result.is_err(), signal == 0);
+    }
+    let error = writer.push(1, &[0.0]).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    let error = writer.finish().unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+
+    // the header only counts the datarecord before, EDFlib keeps the stray samples after it
+    let bytes = fs::read(&path).unwrap();
+    assert_eq!(std::str::from_utf8(&bytes[236..244]).unwrap().trim(), "1");
+}
@@ -0,0 +1,203 @@
+//! Files written with `Edf` read back with what was written.
+
+#![cfg(feature = "ffi")]
+
+mod common;
+
+use std::{ fmt::Debug, fs, path::Path, thread };
+
+use edflib::*;
+
+use common::*;
+
+const DATARECORDS: usize = 4;
+
+fn signals(filetype: Filetype) -> Vec<SignalSpec> {
+    let (digital_minimum, digital_maximum) = filetype.digital_limits();
+    vec![
+        SignalSpec::new(
+            "EEG Fp1".into(),
+            "uV".into(),
+            100.0,
+            (-200.0, 200.0),
+            (digital_minimum, digital_maximum)
+        ),
+        SignalSpec::new("Resp".into(), "mV".into(), 20.0, (-150.0, 150.0), (-2_048, 2_047))
+    ]
+}
+
+fn create(path: &Path, filetype: Filetype) -> Edf {
+    EdfWriterBuilder::new(path.to_path_buf())
+        .filetype(filetype)
+        .signals(signals(filetype))
+        .start(start())
+        .build()
+        .unwrap()
+}
+
+/// Digital test samples, some of them outside of the digital range of the second signal.
+fn digital(signal: usize, record: usize, count: usize) -> Vec<i32> {
+    (0..count)
+        .map(|i| (((signal * 97 + record * 13 + i * 7) % 6_000) as i32) - 3_000)
+        .collect()
+}
+
+/// Writes every datarecord signal by signal with `write`, returns the bytes of the file.
+fn write_by_signal(name: &str, filetype: Filetype, write: impl Fn(&Edf, usize, usize)) -> Vec<u8> {
+    let path = TempPath::new(name);
+    let edf = create(&path, filetype);
+    for record in 0..DATARECORDS {
+        for signal in 0..edf.samples_per_record().len() {
+            write(&edf, signal, record);
+        }
+    }
+    edf.finish().unwrap();
+    fs::read(&path).unwrap()
+}
+
+/// Writes every datarecord at once from a buffer filled by `fill`, returns the bytes of the
+/// file.
+fn write_by_record<T: RecordSample + PartialEq + Debug>(
+    name: &str,
+    filetype: Filetype,
+    fill: impl Fn(&mut RecordBuffer<T>, usize, usize)
+) -> Vec<u8> {
+    let path = TempPath::new(name);
+    let edf = create(&path, filetype);
+    let mut record_buffer = edf.record_buffer::<T>();
+    for record in 0..DATARECORDS {
+        for signal in 0..record_buffer.number_of_signals() {
+            fill(&mut record_buffer, signal, record);
+        }
+        let before = record_buffer.clone();
+        edf.write_record(&record_buffer).unwrap();
+        assert_eq!(record_buffer, before, "write_record changed the buffer");
+    }
+    edf.finish().unwrap();
+    fs::read(&path).unwrap()
+}
+
+#[test]
+fn write_record_physical() {
+    let samples_per_record = [100, 20];
+    let by_record = write_by_record::<f64>("record-physical.edf", Filetype::EDFPLUS, |buf, s, r| {
+        buf.signal_mut(s).copy_from_slice(&samples(s, r, samples_per_record[s]));
+    });
+    let by_signal = write_by_signal("signal-physical.edf", Filetype::EDFPLUS, |edf, s, r| {
+        edf.write_samples(samples(s, r, samples_per_record[s])).unwrap();
+    });
+    assert!(by_record == by_signal, "the files differ");
+
+    let path = TempPath::new("record-physical-read.edf");
+    fs::write(&path, &by_record).unwrap();
+    let reader = EdfReader::open(&path).unwrap();
+    let written: Vec<Vec<f64>> = samples_per_record
+        .iter()
+        .enumerate()
+        .map(|(signal, &count)| {
+            (0..DATARECORDS).flat_map(|record| samples(signal, record, count)).collect()
+        })
+        .collect();
+    assert_samples(&read_signals(&reader), &written, &signals(Filetype::EDFPLUS));
+}
+
+#[test]
+fn write_record_digital() {
+    let samples_per_record = [100, 20];
+    let by_signal = write_by_signal("signal-digital.edf", Filetype::EDFPLUS, |edf, s, r| {
+        edf.write_digital_samples(&digital(s, r, samples_per_record[s])).unwrap();
+    });
+
+    let by_record = write_by_record::<i32>("record-digital.edf", Filetype::EDFPLUS, |buf, s, r| {
+        buf.signal_mut(s).copy_from_slice(&digital(s, r, samples_per_record[s]));
+    });
+    assert!(by_record == by_signal, "the i32 file differs");
+
+    let by_record = write_by_record::<i16>("record-short.edf", Filetype::EDFPLUS, |buf, s, r| {
+        let samples = digital(s, r, samples_per_record[s]);
+        for (target, sample) in buf.signal_mut(s).iter_mut().zip(samples) {
+            *target = sample as i16;
+        }
+    });
+    assert!(by_record == by_signal, "the i16 file differs");
+}
+
+#[test]
+fn write_record_3byte() {
+    let samples_per_record = [100, 20];
+    let by_signal = write_by_signal("signal-3byte.bdf", Filetype::BDFPLUS, |edf, s, r| {
+        edf.write_digital_samples(&digital(s, r, samples_per_record[s])).unwrap();
+    });
+    let by_record = write_by_record::<[u8; 3]>("record-3byte.bdf", Filetype::BDFPLUS, |buf, s, r| {
+        let (digital_minimum, digital_maximum) = signals(Filetype::BDFPLUS)[s].digital_range;
+        let samples = digital(s, r, samples_per_record[s]);
+        for (target, sample) in buf.signal_mut(s).iter_mut().zip(samples) {
+            // 3 byte samples are written as they are, EDFlib does not clamp them
+            let sample = sample.clamp(digital_minimum, digital_maximum);
+            target.copy_from_slice(&sample.to_le_bytes()[..3]);
+        }
+    });
+    assert!(by_record == by_signal, "the files differ");
+}
+
+#[test]
+fn write_record_checks_the_buffer() {
+    let path = TempPath::new("record-checks.edf");
+    let edf = create(&path, Filetype::EDFPLUS);
+
+    let error = edf.write_record(&RecordBuffer::<f64>::new(&[100, 19])).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    let error = edf.write_record(&RecordBuffer::<f64>::new(&[100])).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    let error = edf.write_record(&RecordBuffer::<[u8; 3]>::new(&[100, 20])).unwrap_err();
+    assert!(matches!(error, EdfError::FiletypeError(_)), "{}", error);
+
+    edf.write_samples(samples(0, 0, 100)).unwrap();
+    let error = edf.write_record(&edf.record_buffer::<f64>()).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+}
+
+#[test]
+fn write_samples_checks_the_length() {
+    let path = TempPath::new("samples-length.edf");
+    let edf = create(&path, Filetype::EDFPLUS);
+
+    let too_short = samples(0, 0, 99);
+    let error = edf.write_samples(&too_short).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    assert_eq!(error.context().signal, Some(0));
+
+    // the failed write did not move on to the next signal
+    let first = samples(0, 0, 100);
+    edf.write_samples(first.as_slice()).unwrap();
+    let error = edf.write_samples(samples(1, 0, 100)).unwrap_err();
+    assert!(matches!(error, EdfError::InvalidArgument(..)), "{}", error);
+    let second: [f64; 20] = samples(1, 0, 20).try_into().unwrap();
+    edf.write_samples(second).unwrap();
+    edf.finish().unwrap();
+
+    let reader = EdfReader::open(&path).unwrap();
+    assert_eq!(reader.header().datarecords_in_file, 1);
+    let written = vec![first, second.to_vec()];
+    assert_samples(&read_signals(&reader), &written, &signals(Filetype::EDFPLUS));
+}
+
+#[test]
+fn open_and_close_on_many_threads() {
+    // EDFlib keeps the open files in globals, opening and closing has to be serialized
+    thread::scope(|scope| {
+        for thread in 0..8 {
+            scope.spawn(move || {
+                for file in 0..8 {
+                    let path = TempPath::new(&format!("thread-{}-{}.edf", thread, file));
+                    let edf = create(&path, Filetype::EDFPLUS);
+                    edf.write_samples(samples(0, 0, 100)).unwrap();
+                    edf.write_samples(samples(1, 0, 20)).unwrap();
+                    edf.finish().unwrap();
+                    let reader = EdfReader::open(&path).unwrap();
+                    assert_eq!(reader.header().datarecords_in_file, 1);
+                }
+            });
+        }
+    });
+}